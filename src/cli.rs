@@ -0,0 +1,233 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::{feed, optimizer::Strategy};
+
+/// Analyses an F1 Fantasy season and a private league.
+#[derive(Parser)]
+#[command(
+    version,
+    after_help = "Exit codes: 0 on success, 1 if a command failed, 2 for invalid arguments, \
+                  3 if problems were found in the data."
+)]
+pub struct Cli {
+    /// Directory with the season's data files [default: data]
+    #[arg(long, global = true)]
+    pub data_dir: Option<PathBuf>,
+    /// Season file, `season.toml` in the data directory by default. Data
+    /// files are read from its directory unless `--data-dir` is given.
+    #[arg(long, global = true)]
+    pub season: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Fetch data from the fantasy game into the data files.
+    #[command(subcommand)]
+    Fetch(Fetch),
+    /// Render graphics of the league.
+    #[command(subcommand)]
+    Render(Render),
+    /// List the teams with the best forecast for the next race.
+    Optimize(Optimize),
+    /// Plan the teams for the next races, transfers included.
+    Plan(Plan),
+    /// Check the data files and league files for consistency.
+    Validate,
+    /// Show the state of the league.
+    #[command(subcommand)]
+    League(LeagueCommand),
+    /// Sum up the points of each kind of scoring event.
+    Events,
+    /// Predict price changes with the `[price_changes]` model of the season file
+    /// and report how accurate the predictions were.
+    Prices,
+}
+
+#[derive(Subcommand)]
+pub enum Fetch {
+    /// Fetch the points and prices of race weeks.
+    Weeks {
+        /// A week index or race name, a range like `3..7` or `3..=7`, or
+        /// `latest`.
+        #[arg(required_unless_present = "backfill", conflicts_with = "backfill")]
+        weeks: Option<String>,
+        /// Fetch all complete weeks that are missing from the data files.
+        #[arg(long)]
+        backfill: bool,
+        /// Reuse responses archived earlier today, to only fetch what failed.
+        #[arg(long)]
+        resume: bool,
+        #[command(flatten)]
+        feed: FeedOptions,
+        #[command(flatten)]
+        output: WriteOptions,
+    },
+    /// Match the roster against the game's player list and write the fantasy
+    /// ids into the season file.
+    Ids {
+        /// Saved copy of the player list to read instead of the feed.
+        #[arg(long)]
+        player_list: Option<PathBuf>,
+        #[command(flatten)]
+        feed: FeedOptions,
+        #[command(flatten)]
+        output: WriteOptions,
+    },
+    /// Fetch the teams of all members of a private league into the league
+    /// files.
+    League {
+        league_id: usize,
+        /// Directory with recorded responses to read instead of the game's
+        /// services.
+        #[arg(long)]
+        archive: Option<PathBuf>,
+        /// Session cookie of a logged in user.
+        #[arg(long, env = "F1_FANTASY_COOKIE", hide_env_values = true)]
+        cookie: Option<String>,
+        #[arg(long, default_value = feed::SERVICES_URL)]
+        services_url: String,
+        #[command(flatten)]
+        retry: RetryOptions,
+        #[command(flatten)]
+        output: WriteOptions,
+    },
+    /// Rebuild the data files from an archive of raw feed responses.
+    Import {
+        /// Archive directory, e.g. `data/archive/2024-06-30`.
+        dir: PathBuf,
+    },
+}
+
+#[derive(Args)]
+pub struct FeedOptions {
+    #[arg(long, default_value = feed::FEED_URL)]
+    pub feed_url: String,
+    /// Fetch through a headless Chrome, needs a build with the `chrome`
+    /// feature.
+    #[arg(long)]
+    pub chrome: bool,
+    #[command(flatten)]
+    pub retry: RetryOptions,
+}
+
+#[derive(Args)]
+pub struct RetryOptions {
+    /// Retries of a failed request.
+    #[arg(long, default_value_t = feed::DEFAULT_RETRIES)]
+    pub retries: u32,
+    /// Wait before the first retry in milliseconds, doubled for every further
+    /// one.
+    #[arg(long, default_value_t = feed::DEFAULT_BACKOFF.as_millis() as u64)]
+    pub backoff: u64,
+    /// Minimum time between two requests in milliseconds.
+    #[arg(long, default_value_t = feed::DEFAULT_REQUEST_INTERVAL.as_millis() as u64)]
+    pub request_interval: u64,
+}
+
+#[derive(Args)]
+pub struct WriteOptions {
+    /// Print the changes instead of writing them.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Render {
+    /// Table with the points, budget and team of every member.
+    Overview {
+        #[command(flatten)]
+        week: WeekOption,
+        #[arg(short, long, default_value = "overview.svg")]
+        output: PathBuf,
+    },
+    /// Chart of every member's distance to the first place.
+    Chart {
+        #[arg(short, long, default_value = "distance_to_first.svg")]
+        output: PathBuf,
+    },
+}
+
+#[derive(Args)]
+pub struct WeekOption {
+    /// Week index or race name, the latest week by default.
+    #[arg(short, long)]
+    pub week: Option<String>,
+}
+
+#[derive(Args)]
+pub struct Optimize {
+    /// Maximum cost of a team at the latest prices. Defaults to the budget of
+    /// the team given with `--from`, or 100.
+    #[arg(long)]
+    pub budget: Option<f32>,
+    /// League member whose current team the transfers start from, by the name
+    /// of their league file.
+    #[arg(long)]
+    pub from: Option<String>,
+    /// Most transfers to make from the team given with `--from`.
+    #[arg(long, default_value_t = 3, requires = "from")]
+    pub max_transfers: usize,
+    /// How past weeks are weighted to forecast the next race.
+    #[arg(long, value_enum, default_value_t = Strategy::SpreadWeeks)]
+    pub strategy: Strategy,
+    /// Number of teams to list.
+    #[arg(long, default_value_t = 20)]
+    pub top: usize,
+    /// Drivers or constructors every team must have, e.g. `VER,Ferrari`.
+    #[arg(long, value_delimiter = ',')]
+    pub require: Vec<String>,
+    /// Drivers or constructors no team may have.
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<String>,
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    pub format: Format,
+}
+
+#[derive(Args)]
+pub struct Plan {
+    /// Number of races to plan.
+    #[arg(long, default_value_t = 3, conflicts_with = "projections")]
+    pub races: usize,
+    /// Projected points of the next races, one row per race with a column for
+    /// every driver and constructor. By default every race is projected from
+    /// the past weeks.
+    #[arg(long)]
+    pub projections: Option<PathBuf>,
+    /// How past weeks are weighted to project the next races.
+    #[arg(long, value_enum, default_value_t = Strategy::SpreadWeeks)]
+    pub strategy: Strategy,
+    /// Maximum cost of a team at the latest prices. Defaults to the budget of
+    /// the team given with `--from`, or 100.
+    #[arg(long)]
+    pub budget: Option<f32>,
+    /// League member whose current team the plan starts from, by the name of
+    /// their league file. Without it, the first team is picked for free.
+    #[arg(long)]
+    pub from: Option<String>,
+    /// Most transfers to make before a race.
+    #[arg(long, default_value_t = 3)]
+    pub max_transfers: usize,
+    /// Number of the best teams of every race to plan with.
+    #[arg(long, default_value_t = 100)]
+    pub pool: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Table,
+    Csv,
+    Json,
+}
+
+#[derive(Subcommand)]
+pub enum LeagueCommand {
+    /// Points, transfers and budget of every member.
+    Standings {
+        #[command(flatten)]
+        week: WeekOption,
+    },
+}
@@ -1,129 +1,501 @@
-use std::{iter::zip, path::Path, str::FromStr};
+use std::{
+    fmt::Display,
+    iter::zip,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use crate::{
+    error::{Error, Result},
+    season::Season,
     team::{driver_from_name, Chip, ExtendedTeam, Team},
-    week::{WeekCosts, WeekPoints},
+    week::{Grid, WeekCosts, WeekPoints},
 };
 
-pub fn points() -> Vec<WeekPoints> {
-    zip(
-        driver_points(),
+/// A data file that couldn't be loaded, with the location of the problem.
+#[derive(Debug)]
+pub struct DataError {
+    pub file: PathBuf,
+    pub line: Option<u64>,
+    pub column: Option<String>,
+    pub kind: DataErrorKind,
+}
+
+#[derive(Debug)]
+pub enum DataErrorKind {
+    Csv(csv::Error),
+    UnknownColumn,
+    MissingColumn,
+    DuplicateColumn,
+    InvalidValue(String),
+    Invalid(Box<Error>),
+    Unavailable(String),
+    Inconsistent(String),
+}
+
+impl DataError {
+    pub(crate) fn new(file: &Path, kind: DataErrorKind) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            line: None,
+            column: None,
+            kind,
+        }
+    }
+
+    pub(crate) fn at(mut self, line: Option<u64>, column: &str) -> Self {
+        self.line = line;
+        self.column = Some(column.to_string());
+        self
+    }
+
+    pub(crate) fn at_line(mut self, line: Option<u64>) -> Self {
+        self.line = line;
+        self
+    }
+}
+
+impl Display for DataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+        }
+        if let Some(column) = &self.column {
+            write!(f, ", column {column}")?;
+        }
+        match &self.kind {
+            DataErrorKind::Csv(err) => write!(f, ": {err}"),
+            DataErrorKind::UnknownColumn => write!(f, ": unknown column"),
+            DataErrorKind::MissingColumn => write!(f, ": missing column"),
+            DataErrorKind::DuplicateColumn => write!(f, ": column appears more than once"),
+            DataErrorKind::InvalidValue(value) => write!(f, ": invalid value {value:?}"),
+            DataErrorKind::Invalid(err) => write!(f, ": {err}"),
+            DataErrorKind::Unavailable(driver) => {
+                write!(f, ": {driver} isn't available in this week")
+            }
+            DataErrorKind::Inconsistent(message) => write!(f, ": {message}"),
+        }
+    }
+}
+
+impl std::error::Error for DataError {}
+
+pub fn points(season: &Season) -> Result<Vec<WeekPoints>> {
+    let no_constrs = vec![0; season.constructors.len()];
+    let drivers_only = |drivers| Grid {
+        drivers,
+        constrs: no_constrs.clone(),
+    };
+    Ok(zip(
+        zip(
+            zip(driver_points(season)?, constructor_points(season)?),
+            zip(
+                driver_negative_points(season)?,
+                constructor_negative_points(season)?,
+            ),
+        ),
         zip(
-            constructor_points(),
+            driver_qualifying_points(season)?,
             zip(
-                driver_qualifying_points(),
-                    zip(driver_negative_points(), constructor_negative_points()),
+                driver_sprint_qualifying_points(season)?,
+                driver_sprint_points(season)?,
             ),
         ),
     )
-    .map(|(a, (b, (c, (e, f))))| WeekPoints {
-        drivers: a,
-        constrs: b,
-        drivers_qualifying: c,
-        drivers_negative: e,
-        constrs_negative: f,
+    .map(|(((a, b), (e, f)), (c, (g, h)))| {
+        WeekPoints::new(
+            Grid {
+                drivers: a,
+                constrs: b,
+            },
+            drivers_only(c),
+            drivers_only(g),
+            drivers_only(h),
+            Grid {
+                drivers: e,
+                constrs: f,
+            },
+        )
     })
-    .collect()
+    .collect())
 }
 
-pub fn costs() -> Vec<WeekCosts> {
-    zip(driver_costs(), constructor_costs())
+pub fn costs(season: &Season) -> Result<Vec<WeekCosts>> {
+    Ok(zip(driver_costs(season)?, constructor_costs(season)?)
         .map(|(a, b)| WeekCosts {
             drivers: a,
             constrs: b,
         })
-        .collect()
+        .collect())
 }
 
-pub const DRIVER_POINTS_FILE: &str = "data/drivers_points.csv";
-pub const CONSTRUCTOR_POINTS_FILE: &str = "data/constr_points.csv";
-pub const DRIVER_QUALI_FILE: &str = "data/drivers_qualifying.csv";
-pub const DRIVER_NEGATIVE_FILE: &str = "data/drivers_negative.csv";
-pub const CONSTRUCTOR_NEGATIVE_FILE: &str = "data/constr_negative.csv";
-pub const DRIVER_COST_FILE: &str = "data/drivers_cost.csv";
-pub const CONSTRUCTOR_COST_FILE: &str = "data/constr_cost.csv";
+pub const DRIVER_POINTS_FILE: &str = "drivers_points.csv";
+pub const CONSTRUCTOR_POINTS_FILE: &str = "constr_points.csv";
+pub const DRIVER_QUALI_FILE: &str = "drivers_qualifying.csv";
+pub const DRIVER_SPRINT_QUALI_FILE: &str = "drivers_sprint_qualifying.csv";
+pub const DRIVER_SPRINT_FILE: &str = "drivers_sprint.csv";
+pub const DRIVER_NEGATIVE_FILE: &str = "drivers_negative.csv";
+pub const CONSTRUCTOR_NEGATIVE_FILE: &str = "constr_negative.csv";
+pub const DRIVER_COST_FILE: &str = "drivers_cost.csv";
+pub const CONSTRUCTOR_COST_FILE: &str = "constr_cost.csv";
+pub const LEAGUE_DIR: &str = "league";
 
-pub fn driver_points() -> Vec<[isize; 20]> {
-    read_file(DRIVER_POINTS_FILE)
+pub fn player_file(season: &Season, name: &str) -> PathBuf {
+    season.data_file(LEAGUE_DIR).join(format!("{name}.csv"))
 }
 
-pub fn constructor_points() -> Vec<[isize; 10]> {
-    read_file(CONSTRUCTOR_POINTS_FILE)
+/// League files of all members, sorted by name. Without a league directory
+/// there are none.
+pub fn league_files(season: &Season) -> Result<Vec<PathBuf>> {
+    let dir = season.data_file(LEAGUE_DIR);
+    let io_error = |err| Error::Io(dir.clone(), err);
+    let mut files = Vec::new();
+    if !dir.exists() {
+        return Ok(files);
+    }
+    for entry in std::fs::read_dir(&dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        if path.extension().is_some_and(|e| e == "csv") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
 }
 
-pub fn driver_qualifying_points() -> Vec<[isize; 20]> {
-    read_file(DRIVER_QUALI_FILE)
+/// Names of all members with a league file, see [`player_file`].
+pub fn league_members(season: &Season) -> Result<Vec<String>> {
+    Ok(league_files(season)?
+        .iter()
+        .filter_map(|file| file.file_stem())
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect())
 }
 
-pub fn driver_negative_points() -> Vec<[isize; 20]> {
-    read_file(DRIVER_NEGATIVE_FILE)
+pub fn driver_points(season: &Season) -> Result<Vec<Vec<isize>>> {
+    read_driver_file(season, season.data_file(DRIVER_POINTS_FILE))
 }
 
-pub fn constructor_negative_points() -> Vec<[isize; 10]> {
-    read_file(CONSTRUCTOR_NEGATIVE_FILE)
+pub fn constructor_points(season: &Season) -> Result<Vec<Vec<isize>>> {
+    Ok(read_file(
+        season.data_file(CONSTRUCTOR_POINTS_FILE),
+        &season.constructor_names(),
+    )?)
 }
 
-pub fn driver_costs() -> Vec<[f32; 20]> {
-    read_file(DRIVER_COST_FILE)
+pub fn driver_qualifying_points(season: &Season) -> Result<Vec<Vec<isize>>> {
+    read_driver_file(season, season.data_file(DRIVER_QUALI_FILE))
 }
 
-pub fn constructor_costs() -> Vec<[f32; 10]> {
-    read_file(CONSTRUCTOR_COST_FILE)
+pub fn driver_sprint_qualifying_points(season: &Season) -> Result<Vec<Vec<isize>>> {
+    read_driver_file(season, season.data_file(DRIVER_SPRINT_QUALI_FILE))
 }
 
-fn read_file<const N: usize, T, P>(file: P) -> Vec<[T; N]>
+pub fn driver_sprint_points(season: &Season) -> Result<Vec<Vec<isize>>> {
+    read_driver_file(season, season.data_file(DRIVER_SPRINT_FILE))
+}
+
+pub fn driver_negative_points(season: &Season) -> Result<Vec<Vec<isize>>> {
+    read_driver_file(season, season.data_file(DRIVER_NEGATIVE_FILE))
+}
+
+pub fn constructor_negative_points(season: &Season) -> Result<Vec<Vec<isize>>> {
+    Ok(read_file(
+        season.data_file(CONSTRUCTOR_NEGATIVE_FILE),
+        &season.constructor_names(),
+    )?)
+}
+
+/// Prices of all drivers, including those that aren't available in a week:
+/// a team that still holds such a driver sells him at his real price.
+pub fn driver_costs(season: &Season) -> Result<Vec<Vec<f32>>> {
+    Ok(read_file(
+        season.data_file(DRIVER_COST_FILE),
+        &season.driver_names(),
+    )?)
+}
+
+pub fn constructor_costs(season: &Season) -> Result<Vec<Vec<f32>>> {
+    Ok(read_file(
+        season.data_file(CONSTRUCTOR_COST_FILE),
+        &season.constructor_names(),
+    )?)
+}
+
+/// Reads projected points of upcoming races, one row per race with a column
+/// for every driver and constructor.
+pub fn projections<P: AsRef<Path>>(season: &Season, file: P) -> Result<Vec<Grid<f32>>> {
+    let drivers = season.driver_names();
+    let columns = [drivers.clone(), season.constructor_names()].concat();
+    Ok(read_file(file, &columns)?
+        .into_iter()
+        .map(|row| Grid {
+            drivers: row[..drivers.len()].to_vec(),
+            constrs: row[drivers.len()..].to_vec(),
+        })
+        .collect())
+}
+
+/// Contents of all points and costs files for the given weeks, to be written
+/// with [`replace_files`].
+pub fn data_files(
+    season: &Season,
+    points: &[WeekPoints],
+    costs: &[WeekCosts],
+) -> Vec<(PathBuf, String)> {
+    let drivers = season.driver_names();
+    let constrs = season.constructor_names();
+    let rows = |grid: fn(&WeekPoints) -> &[isize]| points.iter().map(grid).collect::<Vec<_>>();
+
+    vec![
+        (
+            season.data_file(DRIVER_COST_FILE),
+            file_content(
+                &drivers,
+                &costs.iter().map(|c| &c.drivers[..]).collect::<Vec<_>>(),
+            ),
+        ),
+        (
+            season.data_file(CONSTRUCTOR_COST_FILE),
+            file_content(
+                &constrs,
+                &costs.iter().map(|c| &c.constrs[..]).collect::<Vec<_>>(),
+            ),
+        ),
+        (
+            season.data_file(DRIVER_POINTS_FILE),
+            file_content(&drivers, &rows(|p| &p.total.drivers)),
+        ),
+        (
+            season.data_file(DRIVER_NEGATIVE_FILE),
+            file_content(&drivers, &rows(|p| &p.negative.drivers)),
+        ),
+        (
+            season.data_file(DRIVER_QUALI_FILE),
+            file_content(&drivers, &rows(|p| &p.qualifying.drivers)),
+        ),
+        (
+            season.data_file(DRIVER_SPRINT_QUALI_FILE),
+            file_content(&drivers, &rows(|p| &p.sprint_qualifying.drivers)),
+        ),
+        (
+            season.data_file(DRIVER_SPRINT_FILE),
+            file_content(&drivers, &rows(|p| &p.sprint.drivers)),
+        ),
+        (
+            season.data_file(CONSTRUCTOR_POINTS_FILE),
+            file_content(&constrs, &rows(|p| &p.total.constrs)),
+        ),
+        (
+            season.data_file(CONSTRUCTOR_NEGATIVE_FILE),
+            file_content(&constrs, &rows(|p| &p.negative.constrs)),
+        ),
+    ]
+}
+
+/// Formats a file with a header of `columns` and one line per row.
+fn file_content<D: Display>(columns: &[&str], rows: &[&[D]]) -> String {
+    let mut content = columns.join(",");
+    content.push('\n');
+    for row in rows {
+        let line = row.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        content.push_str(&line.join(","));
+        content.push('\n');
+    }
+    content
+}
+
+/// Replaces the content of several files at once. Every file is written to a
+/// temporary file next to it first and they are only renamed into place once
+/// all of them were written, so a failure doesn't leave the data half updated.
+pub fn replace_files(files: &[(PathBuf, String)]) -> Result<()> {
+    let temp_file = |file: &Path| {
+        let mut temp = file.as_os_str().to_owned();
+        temp.push(".tmp");
+        PathBuf::from(temp)
+    };
+    for (file, content) in files {
+        let temp = temp_file(file);
+        if let Err(err) = std::fs::write(&temp, content) {
+            for (file, _) in files {
+                let _ = std::fs::remove_file(temp_file(file));
+            }
+            return Err(Error::Io(temp, err));
+        }
+    }
+    for (file, _) in files {
+        std::fs::rename(temp_file(file), file).map_err(|err| Error::Io(file.clone(), err))?;
+    }
+    Ok(())
+}
+
+/// Reads a file with points of every driver. Points of drivers that aren't
+/// part of the pool in a week are ignored and read as the default value.
+fn read_driver_file<T, P>(season: &Season, file: P) -> Result<Vec<Vec<T>>>
 where
     T: FromStr + Default + Copy,
-    <T as FromStr>::Err: std::fmt::Debug,
     P: AsRef<Path>,
 {
+    let mut rows = read_file(file, &season.driver_names())?;
+    for (week, row) in rows.iter_mut().enumerate() {
+        for (driver, value) in row.iter_mut().enumerate() {
+            if !season.driver_available(driver, week) {
+                *value = T::default();
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/// Reads a file with one column per name in `columns`. Every name has to
+/// appear exactly once in the header and the values of a row are placed by
+/// matching the header against `columns`, so the order of the columns in the
+/// file doesn't matter. Empty cells are read as the default value.
+fn read_file<T, P>(file: P, columns: &[&str]) -> std::result::Result<Vec<Vec<T>>, DataError>
+where
+    T: FromStr + Default + Copy,
+    P: AsRef<Path>,
+{
+    let file = file.as_ref();
     let mut reader = csv::ReaderBuilder::new()
         .has_headers(true)
         .from_path(file)
-        .expect("Couldn't open data file");
+        .map_err(|err| DataError::new(file, DataErrorKind::Csv(err)))?;
+
+    let header = reader
+        .headers()
+        .map_err(|err| DataError::new(file, DataErrorKind::Csv(err)))?
+        .clone();
+    let mut positions = Vec::with_capacity(header.len());
+    for name in header.iter() {
+        let position = columns
+            .iter()
+            .position(|&c| c == name)
+            .ok_or_else(|| DataError::new(file, DataErrorKind::UnknownColumn).at(Some(1), name))?;
+        if positions.contains(&position) {
+            return Err(DataError::new(file, DataErrorKind::DuplicateColumn).at(Some(1), name));
+        }
+        positions.push(position);
+    }
+    if let Some(missing) = columns
+        .iter()
+        .find(|&&c| !header.iter().any(|name| name == c))
+    {
+        return Err(DataError::new(file, DataErrorKind::MissingColumn).at(Some(1), missing));
+    }
 
-    reader
-        .records()
-        .map(|record| {
-            let r = record.unwrap();
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let r = record.map_err(|err| DataError::new(file, DataErrorKind::Csv(err)))?;
+        let line = r.position().map(|p| p.line());
 
-            let mut array = [T::default(); N];
-            for (driver, value) in r.iter().enumerate() {
-                array[driver] = value.parse::<T>().unwrap();
+        let mut row = vec![T::default(); columns.len()];
+        for (&position, value) in zip(&positions, r.iter()) {
+            if !value.is_empty() {
+                row[position] = value.parse::<T>().map_err(|_| {
+                    DataError::new(file, DataErrorKind::InvalidValue(value.to_string()))
+                        .at(line, columns[position])
+                })?;
             }
-            array
-        })
-        .collect()
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+const PLAYER_HEADER: &str =
+    "Driver1,Driver2,Driver3,Driver4,Driver5,Constr1,Constr2,DRS,Chip,Transfers";
+
+/// Formats the teams of a player, one per week, as the content of a league
+/// file.
+pub fn player_file_content(season: &Season, teams: &[ExtendedTeam]) -> String {
+    let mut content = PLAYER_HEADER.to_string();
+    content.push('\n');
+    for t in teams {
+        let mut row = t
+            .team
+            .drivers()
+            .iter()
+            .map(|&d| season.driver_name(d).to_string())
+            .collect::<Vec<_>>();
+        row.extend(
+            t.team
+                .constructors()
+                .iter()
+                .map(|&c| season.constructor_name(c).to_string()),
+        );
+        row.push(season.driver_name(t.drs_driver).to_string());
+        row.push(
+            t.chip
+                .as_ref()
+                .map_or("None".to_string(), |chip| chip.to_input(season)),
+        );
+        row.push(t.negative.to_string());
+        content.push_str(&row.join(","));
+        content.push('\n');
+    }
+    content
 }
 
-pub fn player_data(name: &str) -> Vec<ExtendedTeam> {
+pub fn player_data(season: &Season, name: &str) -> Result<Vec<ExtendedTeam>> {
+    let file = player_file(season, name);
     let mut reader = csv::ReaderBuilder::new()
         .has_headers(true)
-        .from_path(&format!("data/league/{}.csv", name))
-        .expect("Couldn't open player's data file");
-    reader
-        .records()
-        .map(|record| {
-            let r = record.unwrap();
-            let mut team = Team::new();
-            team = team.set_driver_name(&r[0]);
-            team = team.set_driver_name(&r[1]);
-            team = team.set_driver_name(&r[2]);
-            team = team.set_driver_name(&r[3]);
-            team = team.set_driver_name(&r[4]);
-            team = team.set_constructor_name(&r[5]);
-            team = team.set_constructor_name(&r[6]);
-
-            let drs_driver = driver_from_name(&r[7]);
-            let chip = Chip::from_input(&r[8]);
-            let negative = r[9].parse().expect("Invalid amount of transfers");
-
-            ExtendedTeam {
-                team,
-                drs_driver,
-                chip,
-                negative,
+        .from_path(&file)
+        .map_err(|err| DataError::new(&file, DataErrorKind::Csv(err)))?;
+    let header = reader
+        .headers()
+        .map_err(|err| DataError::new(&file, DataErrorKind::Csv(err)))?
+        .clone();
+    if header.len() < 10 {
+        let err = DataError::new(&file, DataErrorKind::MissingColumn).at(Some(1), "Transfers");
+        return Err(err.into());
+    }
+
+    let mut teams = Vec::new();
+    for (week, record) in reader.records().enumerate() {
+        let r = record.map_err(|err| DataError::new(&file, DataErrorKind::Csv(err)))?;
+        let line = r.position().map(|p| p.line());
+        let invalid = |column: usize, err: Error| {
+            DataError::new(&file, DataErrorKind::Invalid(Box::new(err))).at(line, &header[column])
+        };
+
+        let mut team = Team::new();
+        for column in 0..5 {
+            team = team
+                .set_driver_name(season, &r[column])
+                .map_err(|err| invalid(column, err))?;
+        }
+        for column in 5..7 {
+            team = team
+                .set_constructor_name(season, &r[column])
+                .map_err(|err| invalid(column, err))?;
+        }
+
+        let drs_driver = driver_from_name(season, &r[7]).map_err(|err| invalid(7, err))?;
+        let chip = Chip::from_input(season, &r[8]).map_err(|err| invalid(8, err))?;
+        let negative = r[9].parse().map_err(|_| {
+            DataError::new(&file, DataErrorKind::InvalidValue(r[9].to_string()))
+                .at(line, &header[9])
+        })?;
+
+        // A team may still hold a driver who isn't available, he just
+        // scores nothing. Only drivers brought in have to be available.
+        if let Some(Chip::FinalFix(_, race_driver)) = chip {
+            if !season.driver_available(race_driver, week) {
+                let name = season.driver_name(race_driver).to_string();
+                let err = DataError::new(&file, DataErrorKind::Unavailable(name)).at_line(line);
+                return Err(err.into());
             }
-        })
-        .collect()
+        }
+
+        teams.push(ExtendedTeam {
+            team,
+            drs_driver,
+            chip,
+            negative,
+        });
+    }
+    Ok(teams)
 }
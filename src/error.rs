@@ -0,0 +1,90 @@
+use std::{fmt::Display, path::PathBuf};
+
+use crate::{data::DataError, fetch_data::FetchFailure};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, std::io::Error),
+    Data(DataError),
+    Parse(String),
+    UnknownDriver(String),
+    UnknownConstructor(String),
+    UnknownChip(String),
+    Template(String),
+    MissingLabel(String),
+    Fetch(String),
+    HttpStatus(String, u16),
+    FetchFailures(Vec<FetchFailure>),
+    Argument(String),
+    Validation(usize),
+    UnmatchedIds(usize),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            Error::Data(err) => write!(f, "{err}"),
+            Error::Parse(message) => write!(f, "{message}"),
+            Error::UnknownDriver(name) => write!(f, "unknown driver {name:?}"),
+            Error::UnknownConstructor(name) => write!(f, "unknown constructor {name:?}"),
+            Error::UnknownChip(name) => write!(f, "unknown chip {name:?}"),
+            Error::Template(message) => write!(f, "invalid template: {message}"),
+            Error::MissingLabel(label) => write!(f, "template has no element labeled {label:?}"),
+            Error::Fetch(message) => write!(f, "fetching data failed: {message}"),
+            Error::HttpStatus(url, status) => {
+                write!(f, "fetching data failed: {url}: status code {status}")
+            }
+            Error::FetchFailures(failures) => {
+                write!(f, "fetching data failed for {} player(s):", failures.len())?;
+                for failure in failures {
+                    write!(
+                        f,
+                        "\n  {} (id {}): {}",
+                        failure.name, failure.fantasy_id, failure.error
+                    )?;
+                }
+                write!(f, "\nrerun with --resume to fetch only these")
+            }
+            Error::Argument(message) => write!(f, "{message}"),
+            Error::Validation(count) => write!(f, "found {count} problem(s) in the data"),
+            Error::UnmatchedIds(count) => write!(
+                f,
+                "couldn't match {count} player(s) to the player list, set their ids by hand"
+            ),
+        }
+    }
+}
+
+impl Error {
+    /// Exit code of the binary: 2 for invalid arguments like clap's usage
+    /// errors, 3 when a check found problems in the data and 1 otherwise.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Argument(_) => 2,
+            Error::Validation(_) | Error::UnmatchedIds(_) => 3,
+            _ => 1,
+        }
+    }
+
+    /// Whether a failed request may succeed when sent again: the connection
+    /// failed, the server is busy or had an error. Other error statuses like
+    /// 404 will fail the same way again.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Fetch(_) => true,
+            Error::HttpStatus(_, status) => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<DataError> for Error {
+    fn from(err: DataError) -> Self {
+        Error::Data(err)
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    data::{DataError, DataErrorKind},
+    error::{Error, Result},
+    season::Season,
+    week::{Grid, WeekPoints},
+};
+
+/// Every scoring event of every week in long format, one row per event of a
+/// driver or constructor.
+pub const EVENTS_FILE: &str = "events.csv";
+const EVENTS_HEADER: &str = "Week,Kind,Name,Event,Value";
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Entity {
+    Driver(usize),
+    Constructor(usize),
+}
+
+/// A single line of a player's scoring breakdown, e.g. the overtake points
+/// of a driver in a week.
+#[derive(Clone, Debug)]
+pub struct ScoringEvent {
+    pub week: usize,
+    pub entity: Entity,
+    pub event: String,
+    pub value: isize,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Session {
+    Qualifying,
+    SprintQualifying,
+    Sprint,
+    Race,
+}
+
+/// Assigns a scoring event of the playerstats feed to the session it was
+/// scored in.
+pub fn session_of(event: &str) -> Session {
+    if event.starts_with("Sprint Qualifying")
+        || event.starts_with("SQ")
+        || event.starts_with("DQ SQ")
+    {
+        Session::SprintQualifying
+    } else if event.starts_with("Sprint") || event.starts_with("DQ Sprint") {
+        Session::Sprint
+    } else if event == "Qualifying Position"
+        || event.starts_with("QF not classified")
+        || event.starts_with("DQ QF")
+    {
+        Session::Qualifying
+    } else {
+        Session::Race
+    }
+}
+
+/// Points of a driver or constructor in a week, split like [`WeekPoints`].
+#[derive(Default, Clone, Copy, Debug)]
+pub struct PointSummary {
+    pub total: isize,
+    pub qualifying: isize,
+    pub sprint_qualifying: isize,
+    pub sprint: isize,
+    pub negative: isize,
+}
+
+impl PointSummary {
+    pub fn add(&mut self, event: &str, value: isize) {
+        self.total += value;
+        if value < 0 {
+            self.negative -= value;
+        }
+        match session_of(event) {
+            Session::Qualifying => self.qualifying += value,
+            Session::SprintQualifying => self.sprint_qualifying += value,
+            Session::Sprint => self.sprint += value,
+            Session::Race => {}
+        }
+    }
+}
+
+/// Builds the points of a week from the summaries of all drivers and
+/// constructors. Like the CSV files, only the drivers' points are split into
+/// sessions.
+pub fn week_points(drivers: &[PointSummary], constrs: &[PointSummary]) -> WeekPoints {
+    let drivers_only = |value: fn(&PointSummary) -> isize| Grid {
+        drivers: drivers.iter().map(value).collect(),
+        constrs: vec![0; constrs.len()],
+    };
+    WeekPoints::new(
+        Grid {
+            drivers: drivers.iter().map(|d| d.total).collect(),
+            constrs: constrs.iter().map(|c| c.total).collect(),
+        },
+        drivers_only(|d| d.qualifying),
+        drivers_only(|d| d.sprint_qualifying),
+        drivers_only(|d| d.sprint),
+        Grid {
+            drivers: drivers.iter().map(|d| d.negative).collect(),
+            constrs: constrs.iter().map(|c| c.negative).collect(),
+        },
+    )
+}
+
+/// Rebuilds the points of every week from the scoring events.
+pub fn points_from_events(season: &Season, events: &[ScoringEvent]) -> Vec<WeekPoints> {
+    let weeks = events.iter().map(|e| e.week + 1).max().unwrap_or_default();
+    let mut drivers = vec![vec![PointSummary::default(); season.drivers.len()]; weeks];
+    let mut constrs = vec![vec![PointSummary::default(); season.constructors.len()]; weeks];
+    for e in events {
+        match e.entity {
+            Entity::Driver(d) => drivers[e.week][d].add(&e.event, e.value),
+            Entity::Constructor(c) => constrs[e.week][c].add(&e.event, e.value),
+        }
+    }
+    drivers
+        .iter()
+        .zip(&constrs)
+        .map(|(d, c)| week_points(d, c))
+        .collect()
+}
+
+/// Sums up the points of each kind of event, e.g. all overtake points.
+pub fn totals_by_event(events: &[ScoringEvent]) -> BTreeMap<&str, isize> {
+    let mut totals = BTreeMap::new();
+    for e in events {
+        *totals.entry(e.event.as_str()).or_default() += e.value;
+    }
+    totals
+}
+
+pub fn read_events(season: &Season) -> Result<Vec<ScoringEvent>> {
+    let file = season.data_file(EVENTS_FILE);
+    let file = file.as_path();
+    let csv_error = |err| DataError::new(file, DataErrorKind::Csv(err));
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .from_path(file)
+        .map_err(csv_error)?;
+
+    let mut events = Vec::new();
+    for record in reader.records() {
+        let r = record.map_err(csv_error)?;
+        let line = r.position().map(|p| p.line());
+        let invalid = |column: &str, value: &str| {
+            DataError::new(file, DataErrorKind::InvalidValue(value.to_string())).at(line, column)
+        };
+        let week = r[0].parse().map_err(|_| invalid("Week", &r[0]))?;
+        let entity = match &r[1] {
+            "Driver" => season.driver_index(&r[2]).map(Entity::Driver),
+            "Constructor" => season.constructor_index(&r[2]).map(Entity::Constructor),
+            _ => return Err(invalid("Kind", &r[1]).into()),
+        }
+        .ok_or_else(|| invalid("Name", &r[2]))?;
+        let value = r[4].parse().map_err(|_| invalid("Value", &r[4]))?;
+        events.push(ScoringEvent {
+            week,
+            entity,
+            event: r[3].to_string(),
+            value,
+        });
+    }
+    Ok(events)
+}
+
+/// Formats events as the content of the events file.
+pub fn events_csv(season: &Season, events: &[ScoringEvent]) -> Result<String> {
+    let csv_error = |err: csv::Error| Error::Parse(format!("{EVENTS_FILE}: {err}"));
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new());
+    writer
+        .write_record(EVENTS_HEADER.split(','))
+        .map_err(csv_error)?;
+    for e in events {
+        let (kind, name) = match e.entity {
+            Entity::Driver(d) => ("Driver", season.driver_name(d)),
+            Entity::Constructor(c) => ("Constructor", season.constructor_name(c)),
+        };
+        writer
+            .write_record([
+                &e.week.to_string(),
+                kind,
+                name,
+                &e.event,
+                &e.value.to_string(),
+            ])
+            .map_err(csv_error)?;
+    }
+    let content = writer
+        .into_inner()
+        .map_err(|err| Error::Parse(format!("{EVENTS_FILE}: {err}")))?;
+    String::from_utf8(content).map_err(|err| Error::Parse(format!("{EVENTS_FILE}: {err}")))
+}
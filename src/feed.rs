@@ -0,0 +1,420 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::error::{Error, Result};
+
+/// Root of the fantasy game's JSON feeds.
+pub const FEED_URL: &str = "https://fantasy.formula1.com/feeds/";
+
+/// Source of the fantasy game's JSON feeds.
+pub trait Feed {
+    /// Returns the body of the feed at `path`, relative to the feed root,
+    /// e.g. `popup/playerstats_131.json`.
+    fn get(&mut self, path: &str) -> Result<String>;
+}
+
+impl<F: Feed + ?Sized> Feed for Box<F> {
+    fn get(&mut self, path: &str) -> Result<String> {
+        (**self).get(path)
+    }
+}
+
+fn feed_url(base_url: &str, path: &str) -> String {
+    format!("{}/{}", base_url.trim_end_matches('/'), path)
+}
+
+/// Root of the game's user services, e.g. private leagues, which need the
+/// session cookie of a logged in user.
+pub const SERVICES_URL: &str = "https://fantasy.formula1.com/services/user/";
+
+/// Reads the feeds with plain HTTP requests.
+pub struct HttpFeed {
+    agent: ureq::Agent,
+    base_url: String,
+    cookie: Option<String>,
+}
+
+impl HttpFeed {
+    /// Creates a client for the feeds below `base_url`, which is [`FEED_URL`]
+    /// except when talking to a mirror or a local test server.
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+            base_url: base_url.to_string(),
+            cookie: None,
+        }
+    }
+
+    /// Sends `cookie` with every request, for the services that need a login.
+    pub fn with_cookie(mut self, cookie: String) -> Self {
+        self.cookie = Some(cookie);
+        self
+    }
+}
+
+impl Feed for HttpFeed {
+    fn get(&mut self, path: &str) -> Result<String> {
+        let url = feed_url(&self.base_url, path);
+        let mut request = self.agent.get(&url);
+        if let Some(cookie) = &self.cookie {
+            request = request.set("Cookie", cookie);
+        }
+        request
+            .call()
+            .map_err(|err| match err {
+                ureq::Error::Status(status, _) => Error::HttpStatus(url.clone(), status),
+                err => Error::Fetch(err.to_string()),
+            })?
+            .into_string()
+            .map_err(|err| Error::Fetch(format!("{url}: {err}")))
+    }
+}
+
+pub const DEFAULT_RETRIES: u32 = 3;
+pub const DEFAULT_BACKOFF: Duration = Duration::from_secs(1);
+pub const DEFAULT_REQUEST_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Retries requests of another feed that failed for a transient reason and
+/// keeps a minimum interval between requests, so fetching a whole grid
+/// doesn't hammer the server.
+pub struct RetryingFeed<F> {
+    inner: F,
+    retries: u32,
+    backoff: Duration,
+    interval: Duration,
+    last_request: Option<Instant>,
+}
+
+impl<F: Feed> RetryingFeed<F> {
+    /// Creates a feed that retries a failed request up to `retries` times,
+    /// waiting `backoff` before the first retry and twice as long before each
+    /// further one, and that waits at least `interval` between requests.
+    pub fn new(inner: F, retries: u32, backoff: Duration, interval: Duration) -> Self {
+        Self {
+            inner,
+            retries,
+            backoff,
+            interval,
+            last_request: None,
+        }
+    }
+
+    fn wait_for_interval(&mut self) {
+        if let Some(last) = self.last_request {
+            thread::sleep(self.interval.saturating_sub(last.elapsed()));
+        }
+        self.last_request = Some(Instant::now());
+    }
+}
+
+impl<F: Feed> Feed for RetryingFeed<F> {
+    fn get(&mut self, path: &str) -> Result<String> {
+        let mut delay = self.backoff;
+        let mut attempt = 0;
+        loop {
+            self.wait_for_interval();
+            match self.inner.get(path) {
+                Ok(body) => return Ok(body),
+                Err(err) if attempt < self.retries && err.is_transient() => {
+                    attempt += 1;
+                    eprintln!(
+                        "{path}: {err}, retry {attempt} of {} in {:.1}s",
+                        self.retries,
+                        delay.as_secs_f32()
+                    );
+                    thread::sleep(delay);
+                    delay *= 2;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// Reads the feeds through a headless Chrome, for when plain requests are
+/// blocked.
+#[cfg(feature = "chrome")]
+pub struct ChromeFeed {
+    _browser: headless_chrome::Browser,
+    tab: std::sync::Arc<headless_chrome::Tab>,
+    base_url: String,
+}
+
+#[cfg(feature = "chrome")]
+impl ChromeFeed {
+    pub fn new(base_url: &str) -> Result<Self> {
+        use headless_chrome::{Browser, LaunchOptionsBuilder};
+
+        let launch_options = LaunchOptionsBuilder::default()
+            .headless(true)
+            .build()
+            .map_err(|err| Error::Fetch(err.to_string()))?;
+        let browser = Browser::new(launch_options).map_err(|err| Error::Fetch(err.to_string()))?;
+        let tab = browser
+            .new_tab()
+            .map_err(|err| Error::Fetch(err.to_string()))?;
+        Ok(Self {
+            _browser: browser,
+            tab,
+            base_url: base_url.to_string(),
+        })
+    }
+}
+
+#[cfg(feature = "chrome")]
+impl Feed for ChromeFeed {
+    fn get(&mut self, path: &str) -> Result<String> {
+        let url = feed_url(&self.base_url, path);
+        self.tab
+            .navigate_to(&url)
+            .and_then(|tab| tab.wait_until_navigated())
+            .and_then(|tab| tab.find_element("pre"))
+            .and_then(|e| e.get_inner_text())
+            .map_err(|err| Error::Fetch(format!("{url}: {err}")))
+    }
+}
+
+/// Directory with one subdirectory of raw feed responses per day data was
+/// fetched.
+pub const ARCHIVE_DIR: &str = "archive";
+
+/// Archive directory in `data_dir` for responses fetched today, e.g.
+/// `data/archive/2024-06-30`.
+pub fn todays_archive(data_dir: &Path) -> PathBuf {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or_default() as i64;
+    // Converts days since 1970-01-01 to a civil date, see
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    data_dir
+        .join(ARCHIVE_DIR)
+        .join(format!("{year:04}-{month:02}-{day:02}"))
+}
+
+/// File an archived response is stored in, the last segment of its path.
+fn archive_file(dir: &Path, path: &str) -> PathBuf {
+    dir.join(path.rsplit('/').next().unwrap_or(path))
+}
+
+/// Passes requests through to another feed and stores every response in an
+/// archive directory. A path that was already fetched is answered from the
+/// archive instead of requesting it again.
+pub struct ArchivingFeed<F> {
+    inner: F,
+    dir: PathBuf,
+    archived: HashSet<PathBuf>,
+}
+
+impl<F: Feed> ArchivingFeed<F> {
+    /// Creates an archive in `dir`. With `resume`, responses already in the
+    /// directory, e.g. from an earlier run that failed halfway, are reused
+    /// too, so only the missing ones are requested.
+    pub fn new(inner: F, dir: PathBuf, resume: bool) -> Result<Self> {
+        std::fs::create_dir_all(&dir).map_err(|err| Error::Io(dir.clone(), err))?;
+        let mut archived = HashSet::new();
+        if resume {
+            for entry in std::fs::read_dir(&dir).map_err(|err| Error::Io(dir.clone(), err))? {
+                archived.insert(entry.map_err(|err| Error::Io(dir.clone(), err))?.path());
+            }
+        }
+        Ok(Self {
+            inner,
+            dir,
+            archived,
+        })
+    }
+}
+
+impl<F: Feed> Feed for ArchivingFeed<F> {
+    fn get(&mut self, path: &str) -> Result<String> {
+        let file = archive_file(&self.dir, path);
+        if self.archived.contains(&file) {
+            return std::fs::read_to_string(&file).map_err(|err| Error::Io(file, err));
+        }
+        let body = self.inner.get(path)?;
+        std::fs::write(&file, &body).map_err(|err| Error::Io(file.clone(), err))?;
+        self.archived.insert(file);
+        Ok(body)
+    }
+}
+
+/// Answers requests from an archive directory written by [`ArchivingFeed`],
+/// without any network access.
+pub struct ArchiveFeed {
+    dir: PathBuf,
+}
+
+impl ArchiveFeed {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+impl Feed for ArchiveFeed {
+    fn get(&mut self, path: &str) -> Result<String> {
+        let file = archive_file(&self.dir, path);
+        std::fs::read_to_string(&file).map_err(|err| Error::Io(file, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread::JoinHandle,
+    };
+
+    use super::*;
+    use crate::fetch_data;
+
+    const PLAYERSTATS: &str = include_str!("../tests/fixtures/playerstats_131.json");
+
+    /// Starts a server on a free local port that answers a single request
+    /// with `status` and `body`. Returns the feed root to use and a handle
+    /// that yields the head of the request received.
+    fn serve_once(status: &'static str, body: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/feeds/", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim_end().is_empty() {
+                    break;
+                }
+                head.push_str(&line);
+            }
+            write!(
+                &stream,
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+            head
+        });
+        (base_url, server)
+    }
+
+    #[test]
+    fn http_feed_fetches_path_below_feed_root() {
+        let (base_url, server) = serve_once("200 OK", PLAYERSTATS);
+        let mut feed = HttpFeed::new(&base_url);
+        let body = feed.get(&fetch_data::playerstats_path(131)).unwrap();
+        let head = server.join().unwrap();
+
+        assert!(head.starts_with("GET /feeds/popup/playerstats_131.json HTTP/1.1\r\n"));
+        assert!(!head.to_lowercase().contains("cookie:"));
+        assert_eq!(body, PLAYERSTATS);
+    }
+
+    #[test]
+    fn http_feed_response_parses_as_playerstats() {
+        let (base_url, server) = serve_once("200 OK", PLAYERSTATS);
+        let mut feed = HttpFeed::new(&base_url);
+        let body = feed.get(&fetch_data::playerstats_path(131)).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(fetch_data::completed_gamedays(&body).unwrap(), 2);
+        assert_eq!(fetch_data::extract_price(&body, 0).unwrap(), 30.0);
+        let (events, price) = fetch_data::extract_events(&body, 1).unwrap();
+        assert_eq!(price, 30.4);
+        assert_eq!(
+            events,
+            [
+                ("Qualifying Position".to_string(), 10),
+                ("Race Position".to_string(), 25),
+                ("Race Overtake Bonus".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn http_feed_sends_cookie() {
+        let (base_url, server) = serve_once("200 OK", "{}");
+        let mut feed = HttpFeed::new(&base_url).with_cookie("session=abc".to_string());
+        feed.get("leaderboard/pvtleagueuserrankget_42.json")
+            .unwrap();
+        let head = server.join().unwrap();
+
+        assert!(head.contains("Cookie: session=abc\r\n"));
+    }
+
+    #[test]
+    fn http_feed_reports_error_status() {
+        let (base_url, server) = serve_once("404 Not Found", "");
+        let mut feed = HttpFeed::new(&base_url);
+        let result = feed.get("popup/playerstats_1.json");
+        server.join().unwrap();
+
+        assert!(matches!(result, Err(Error::HttpStatus(_, 404))));
+    }
+
+    /// Answers every request with the next of `responses` and counts them.
+    struct ScriptedFeed {
+        responses: Vec<Result<String>>,
+        requests: usize,
+    }
+
+    impl Feed for ScriptedFeed {
+        fn get(&mut self, _path: &str) -> Result<String> {
+            self.requests += 1;
+            self.responses.remove(0)
+        }
+    }
+
+    fn retrying(responses: Vec<Result<String>>) -> RetryingFeed<ScriptedFeed> {
+        let inner = ScriptedFeed {
+            responses,
+            requests: 0,
+        };
+        RetryingFeed::new(inner, 3, Duration::ZERO, Duration::ZERO)
+    }
+
+    #[test]
+    fn retrying_feed_retries_server_errors() {
+        let mut feed = retrying(vec![
+            Err(Error::HttpStatus("url".to_string(), 503)),
+            Err(Error::HttpStatus("url".to_string(), 429)),
+            Err(Error::Fetch("connection reset".to_string())),
+            Ok("{}".to_string()),
+        ]);
+
+        assert_eq!(feed.get("popup/playerstats_1.json").unwrap(), "{}");
+        assert_eq!(feed.inner.requests, 4);
+    }
+
+    #[test]
+    fn retrying_feed_gives_up_on_client_errors() {
+        let mut feed = retrying(vec![
+            Err(Error::HttpStatus("url".to_string(), 404)),
+            Ok("{}".to_string()),
+        ]);
+
+        assert!(matches!(
+            feed.get("popup/playerstats_1.json"),
+            Err(Error::HttpStatus(_, 404))
+        ));
+        assert_eq!(feed.inner.requests, 1);
+    }
+}
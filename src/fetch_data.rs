@@ -1,108 +1,246 @@
-use std::error::Error;
+use serde_json::Value;
 
-use headless_chrome::{Browser, LaunchOptionsBuilder};
+use crate::{
+    error::{Error, Result},
+    events::{week_points, Entity, PointSummary, ScoringEvent},
+    feed::Feed,
+    season::Season,
+    week::{WeekCosts, WeekPoints},
+};
 
-use crate::week::{WeekCosts, WeekPoints};
-
-pub const DRIVER_IDS: [usize; 20] = [131, 121, 125, 115, 110, 124, 117, 1982, 12, 129, 118, 18, 123, 130, 11, 126, 116, 111, 13, 134];
-pub const CONSTRUCTOR_IDS: [usize; 10] = [29, 25, 28, 27, 24, 23, 2580, 26, 2581, 210];
-
-pub fn fetch_data(week: usize) -> Result<(WeekPoints, WeekCosts), Box<dyn Error>> {
-    let mut launch_options_b = LaunchOptionsBuilder::default();
-    let launch_options = launch_options_b.headless(true).build()?;
-    let browser = Browser::new(launch_options)?;
+pub fn playerstats_path(fantasy_id: usize) -> String {
+    format!("popup/playerstats_{fantasy_id}.json")
+}
 
-    let tab = browser.new_tab()?;
+/// A player whose data couldn't be fetched.
+#[derive(Debug)]
+pub struct FetchFailure {
+    pub name: String,
+    pub fantasy_id: usize,
+    pub error: Error,
+}
 
-    // Navigate to wikipedia
-    let mut total = Vec::new();
-    let mut quali = Vec::new();
-    let mut neg = Vec::new();
-    let mut total_con = Vec::new();
-    let mut neg_con = Vec::new();
+/// Fetches the points, costs and scoring events of all players in a week.
+/// A failing player doesn't stop the others from being fetched, the error
+/// lists all players that failed.
+pub fn fetch_data(
+    feed: &mut dyn Feed,
+    season: &Season,
+    week: usize,
+) -> Result<(WeekPoints, WeekCosts, Vec<ScoringEvent>)> {
+    let mut drivers = Vec::new();
+    let mut constrs = Vec::new();
     let mut cost = Vec::new();
     let mut cost_con = Vec::new();
+    let mut events = Vec::new();
+    let mut failures = Vec::new();
 
-    for driver_id in DRIVER_IDS {
-        tab.navigate_to(&format!("https://fantasy.formula1.com/feeds/popup/playerstats_{driver_id}.json"))?;
-        tab.wait_until_navigated()?;
-        let x = tab.find_element("pre")?.get_inner_text()?;
-        let (t, q, n, c) = extract_driver_data(&x, week)?;
-        total.push(t);
-        quali.push(q);
-        neg.push(n);
+    let mut fetch = |name: &str, fantasy_id: usize| {
+        feed.get(&playerstats_path(fantasy_id))
+            .and_then(|x| extract_events(&x, week))
+            .map_err(|error| {
+                failures.push(FetchFailure {
+                    name: name.to_string(),
+                    fantasy_id,
+                    error,
+                })
+            })
+            .unwrap_or_default()
+    };
+
+    for (index, driver) in season.drivers.iter().enumerate() {
+        if !driver.available(week) {
+            drivers.push(PointSummary::default());
+            cost.push(0.0);
+            continue;
+        }
+        let (stats, c) = fetch(&driver.name, driver.fantasy_id);
+        drivers.push(summarize(&stats));
         cost.push(c);
+        events.extend(scoring_events(stats, week, Entity::Driver(index)));
     }
 
-    for constrtor_id in CONSTRUCTOR_IDS {
-        tab.navigate_to(&format!("https://fantasy.formula1.com/feeds/popup/playerstats_{constrtor_id}.json"))?;
-        tab.wait_until_navigated()?;
-        let x = tab.find_element("pre")?.get_inner_text()?;
-        let (t, n, c) = extract_constructor_data(&x, week)?;
-        total_con.push(t);
-        neg_con.push(n);
+    for (index, constructor) in season.constructors.iter().enumerate() {
+        let (stats, c) = fetch(&constructor.name, constructor.fantasy_id);
+        constrs.push(summarize(&stats));
         cost_con.push(c);
+        events.extend(scoring_events(stats, week, Entity::Constructor(index)));
     }
 
+    // Teams can still hold a driver who isn't available, so his price is
+    // kept. A driver who isn't part of the game yet has none.
+    for (index, driver) in season.drivers.iter().enumerate() {
+        if !driver.available(week) {
+            if let Ok((_, c)) = feed
+                .get(&playerstats_path(driver.fantasy_id))
+                .and_then(|x| extract_events(&x, week))
+            {
+                cost[index] = c;
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(Error::FetchFailures(failures));
+    }
     Ok((
-        WeekPoints {
-            drivers: total.try_into().unwrap(),
-            constrs: total_con.try_into().unwrap(),
-            drivers_negative: neg.try_into().unwrap(),
-            constrs_negative: neg_con.try_into().unwrap(),
-            drivers_qualifying: quali.try_into().unwrap()
-        },
+        week_points(&drivers, &constrs),
         WeekCosts {
-            drivers: cost.try_into().unwrap(),
-            constrs: cost_con.try_into().unwrap(),
-        }
+            drivers: cost,
+            constrs: cost_con,
+        },
+        events,
     ))
 }
 
-pub fn extract_driver_data(input: &str, week: usize) -> Result<(isize, isize, isize, f32), Box<dyn Error>> {
-    let json: serde_json::Value = serde_json::from_str(input)?;
-    let week_data = json.as_object().unwrap()["Value"].as_object().unwrap() .get("GamedayWiseStats").unwrap().as_array().unwrap()[week].as_object().unwrap();
-    let week_data2 = json.as_object().unwrap()["Value"].as_object().unwrap() .get("GamedayWiseStats").unwrap().as_array().unwrap()[week + 1].as_object().unwrap();
-    let cost: f32 = week_data2["PlayerValue"].as_f64().unwrap() as f32;
-    let stats_wise = week_data["StatsWise"].as_array().unwrap();
-    let mut total = 0;
-    let mut quali = 0;
-    let mut negative = 0;
-    for stat in stats_wise {
-        let event = stat["Event"].as_str().unwrap();
-        let value = stat["Value"].as_i64().unwrap();
-        if event == "Total" {
-            continue;
-        }
-        total += value;
-        if value < 0 {
-            negative -= value;
-        }
-        if event == "Qualifying Position" || event.starts_with("QF not classified") || event.starts_with("DQ QF") {
-            quali += value;
-        }
+/// Returns the number of weeks the feed has complete data for, going by the
+/// first constructor, who takes part in every race.
+pub fn completed_weeks(feed: &mut dyn Feed, season: &Season) -> Result<usize> {
+    let reference = season
+        .constructors
+        .first()
+        .ok_or_else(|| Error::Fetch("season has no constructors".to_string()))?;
+    completed_gamedays(&feed.get(&playerstats_path(reference.fantasy_id))?)
+}
+
+/// Rebuilds the points, costs and scoring events of all completed weeks from
+/// a feed, e.g. an archive of raw playerstats responses. Returns one more
+/// costs entry than weeks, starting with the prices before the first race.
+pub fn fetch_season(
+    feed: &mut dyn Feed,
+    season: &Season,
+) -> Result<(Vec<WeekPoints>, Vec<WeekCosts>, Vec<ScoringEvent>)> {
+    let weeks = completed_weeks(feed, season)?;
+    let mut points = Vec::new();
+    let mut costs = vec![initial_prices(feed, season)?];
+    let mut events = Vec::new();
+    for week in 0..weeks {
+        let (p, c, e) = fetch_data(feed, season, week)?;
+        points.push(p);
+        costs.push(c);
+        events.extend(e);
     }
-    Ok((total as isize, quali as isize, negative as isize, cost))
+    Ok((points, costs, events))
 }
 
-pub fn extract_constructor_data(input: &str, week: usize) -> Result<(isize, isize, f32), Box<dyn Error>> {
-    let json: serde_json::Value = serde_json::from_str(input)?;
-    let week_data = json.as_object().unwrap()["Value"].as_object().unwrap() .get("GamedayWiseStats").unwrap().as_array().unwrap()[week].as_object().unwrap();
-    let week_data2 = json.as_object().unwrap()["Value"].as_object().unwrap() .get("GamedayWiseStats").unwrap().as_array().unwrap()[week + 1].as_object().unwrap();
-    let cost: f32 = week_data2["PlayerValue"].as_f64().unwrap() as f32;
-    let stats_wise = week_data["StatsWise"].as_array().unwrap();
-    let mut total = 0;
-    let mut negative = 0;
+/// Fetches the prices of all players before the first race.
+pub fn initial_prices(feed: &mut dyn Feed, season: &Season) -> Result<WeekCosts> {
+    let mut initial = WeekCosts {
+        drivers: Vec::new(),
+        constrs: Vec::new(),
+    };
+    for driver in &season.drivers {
+        let input = feed.get(&playerstats_path(driver.fantasy_id));
+        initial.drivers.push(if driver.available(0) {
+            extract_price(&input?, 0)?
+        } else {
+            input.and_then(|x| extract_price(&x, 0)).unwrap_or(0.0)
+        });
+    }
+    for constructor in &season.constructors {
+        let input = feed.get(&playerstats_path(constructor.fantasy_id))?;
+        initial.constrs.push(extract_price(&input, 0)?);
+    }
+    Ok(initial)
+}
+
+fn summarize(stats: &[(String, isize)]) -> PointSummary {
+    let mut summary = PointSummary::default();
+    for (event, value) in stats {
+        summary.add(event, *value);
+    }
+    summary
+}
+
+fn scoring_events(
+    stats: Vec<(String, isize)>,
+    week: usize,
+    entity: Entity,
+) -> impl Iterator<Item = ScoringEvent> {
+    stats.into_iter().map(move |(event, value)| ScoringEvent {
+        week,
+        entity,
+        event,
+        value,
+    })
+}
+
+fn fetch_error<E: std::fmt::Display>(err: E) -> Error {
+    Error::Fetch(err.to_string())
+}
+
+fn format_error(message: &str) -> Error {
+    Error::Fetch(format!("unexpected playerstats format: {message}"))
+}
+
+fn gamedays(json: &Value) -> Result<&Vec<Value>> {
+    json.get("Value")
+        .and_then(|v| v.get("GamedayWiseStats"))
+        .and_then(Value::as_array)
+        .ok_or_else(|| format_error("missing GamedayWiseStats"))
+}
+
+fn gameday_price(gamedays: &[Value], gameday: usize) -> Result<f32> {
+    gamedays
+        .get(gameday)
+        .and_then(|w| w.get("PlayerValue"))
+        .and_then(Value::as_f64)
+        .map(|price| price as f32)
+        .ok_or_else(|| format_error(&format!("no price for gameday {gameday}")))
+}
+
+/// Returns the stats of the given gameday and the price after it.
+fn gameday_stats(json: &Value, week: usize) -> Result<(&Vec<Value>, f32)> {
+    let gamedays = gamedays(json)?;
+    let week_data = gamedays
+        .get(week)
+        .and_then(Value::as_object)
+        .ok_or_else(|| format_error(&format!("no stats for gameday {week}")))?;
+    let cost = gameday_price(gamedays, week + 1)?;
+    let stats_wise = week_data
+        .get("StatsWise")
+        .and_then(Value::as_array)
+        .ok_or_else(|| format_error("missing StatsWise"))?;
+    Ok((stats_wise, cost))
+}
+
+fn stat_entry(stat: &Value) -> Result<(&str, i64)> {
+    let event = stat
+        .get("Event")
+        .and_then(Value::as_str)
+        .ok_or_else(|| format_error("stat without Event"))?;
+    let value = stat
+        .get("Value")
+        .and_then(Value::as_i64)
+        .ok_or_else(|| format_error("stat without Value"))?;
+    Ok((event, value))
+}
+
+/// Returns the number of gamedays in a playerstats feed that are complete,
+/// i.e. that are followed by the price for the next gameday.
+pub fn completed_gamedays(input: &str) -> Result<usize> {
+    let json: Value = serde_json::from_str(input).map_err(fetch_error)?;
+    Ok(gamedays(&json)?.len().saturating_sub(1))
+}
+
+/// Returns the price of a player going into the given gameday.
+pub fn extract_price(input: &str, gameday: usize) -> Result<f32> {
+    let json: Value = serde_json::from_str(input).map_err(fetch_error)?;
+    gameday_price(gamedays(&json)?, gameday)
+}
+
+/// Returns all scoring events of a player in the given gameday, without the
+/// "Total" entry, and the price for the following week.
+pub fn extract_events(input: &str, week: usize) -> Result<(Vec<(String, isize)>, f32)> {
+    let json: Value = serde_json::from_str(input).map_err(fetch_error)?;
+    let (stats_wise, cost) = gameday_stats(&json, week)?;
+    let mut events = Vec::new();
     for stat in stats_wise {
-        let event = stat["Event"].as_str().unwrap();
-        let value = stat["Value"].as_i64().unwrap();
+        let (event, value) = stat_entry(stat)?;
         if event == "Total" {
             continue;
         }
-        total += value;
-        if value < 0 {
-            negative -= value;
-        }
+        events.push((event.to_string(), value as isize));
     }
-    Ok((total as isize, negative as isize, cost))
-}
\ No newline at end of file
+    Ok((events, cost))
+}
@@ -0,0 +1,398 @@
+use serde_json::Value;
+
+use crate::{
+    error::{Error, Result},
+    feed::Feed,
+    season::Season,
+    team::{Chip, ExtendedTeam, Team},
+};
+
+/// Path of the member list of a private league, relative to
+/// [`crate::feed::SERVICES_URL`].
+pub fn league_members_path(league_id: usize) -> String {
+    format!("leaderboard/pvtleagueuserrankget_{league_id}.json")
+}
+
+/// Path of the team a league member submitted for a gameday.
+pub fn member_team_path(member: &LeagueMember, gameday: usize) -> String {
+    format!(
+        "opponentteam/opponentgamedayplayerteamget_{}_{}_{}.json",
+        member.guid,
+        member.team_no,
+        gameday + 1
+    )
+}
+
+/// A team of a private league. A user can have more than one team, told apart
+/// by the team number.
+#[derive(Clone, Debug)]
+pub struct LeagueMember {
+    pub team_name: String,
+    pub guid: String,
+    pub team_no: usize,
+}
+
+impl LeagueMember {
+    /// Name of the member's league file: the team name in lowercase with
+    /// every run of characters other than letters and digits replaced by a
+    /// single underscore, and none at either end.
+    pub fn file_name(&self) -> String {
+        self.team_name
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join("_")
+    }
+}
+
+/// Letters and digits of a file name, to recognise a league file that was
+/// named differently, e.g. by hand.
+fn file_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Picks the league file of every member: an existing file that only differs
+/// in punctuation from [`LeagueMember::file_name`], or that name for a new
+/// member. Fails if two members would share a file.
+pub fn member_files(members: &[LeagueMember], existing: &[String]) -> Result<Vec<String>> {
+    let mut files: Vec<String> = Vec::with_capacity(members.len());
+    for (index, member) in members.iter().enumerate() {
+        let name = member.file_name();
+        let file = existing
+            .iter()
+            .find(|e| **e == name)
+            .or_else(|| existing.iter().find(|e| file_key(e) == file_key(&name)))
+            .cloned()
+            .unwrap_or(name);
+        if let Some(other) = files.iter().position(|f| *f == file) {
+            return Err(Error::Fetch(format!(
+                "the teams {:?} and {:?} would both be written to {file}.csv",
+                members[other].team_name, members[index].team_name
+            )));
+        }
+        files.push(file);
+    }
+    Ok(files)
+}
+
+fn format_error(message: &str) -> Error {
+    Error::Fetch(format!("unexpected league format: {message}"))
+}
+
+/// Returns the value below `Data.Value`, where the services wrap their
+/// responses, or the whole document.
+fn payload(json: &Value) -> &Value {
+    json.get("Data")
+        .and_then(|d| d.get("Value"))
+        .unwrap_or(json)
+}
+
+fn text(value: &Value, key: &str) -> Option<String> {
+    match value.get(key)? {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+fn number(value: &Value, key: &str) -> Option<i64> {
+    match value.get(key)? {
+        Value::String(text) => text.parse().ok(),
+        value => value.as_i64(),
+    }
+}
+
+fn flag(value: &Value, key: &str) -> bool {
+    number(value, key).is_some_and(|n| n != 0)
+}
+
+/// Parses the member list of a private league, an array `memRank` of entries
+/// with `teamName`, `guid` and `teamNo`.
+pub fn parse_league_members(input: &str) -> Result<Vec<LeagueMember>> {
+    let json: Value = serde_json::from_str(input).map_err(|err| Error::Fetch(err.to_string()))?;
+    let members = payload(&json)
+        .get("memRank")
+        .and_then(Value::as_array)
+        .ok_or_else(|| format_error("missing memRank"))?;
+    members
+        .iter()
+        .map(|member| {
+            Ok(LeagueMember {
+                team_name: text(member, "teamName")
+                    .ok_or_else(|| format_error("member without teamName"))?,
+                guid: text(member, "guid").ok_or_else(|| format_error("member without guid"))?,
+                team_no: number(member, "teamNo").unwrap_or(1) as usize,
+            })
+        })
+        .collect()
+}
+
+/// Parses the team a member submitted for a gameday. The response holds a
+/// `userTeam` entry with the picked players in `playerid`, each with an `id`
+/// and `iscaptain` for the DRS driver or `ismgcaptain` for the Extra DRS
+/// driver. Chips are flags like `islimitless`, with `finalfxoldplayerid` and
+/// `finalfxnewplayerid` for the Final Fix, and `transferpenalty` holds the
+/// points lost for extra transfers. With the Final Fix, the line-up already
+/// contains the new driver, while the team is stored with the replaced driver.
+pub fn parse_member_team(season: &Season, input: &str) -> Result<ExtendedTeam> {
+    let json: Value = serde_json::from_str(input).map_err(|err| Error::Fetch(err.to_string()))?;
+    let team = payload(&json)
+        .get("userTeam")
+        .and_then(Value::as_array)
+        .and_then(|teams| teams.first())
+        .ok_or_else(|| format_error("missing userTeam"))?;
+    let players = team
+        .get("playerid")
+        .and_then(Value::as_array)
+        .ok_or_else(|| format_error("team without playerid"))?;
+
+    let driver_by_id = |id: Option<i64>| {
+        season
+            .drivers
+            .iter()
+            .position(|d| Some(d.fantasy_id as i64) == id)
+    };
+    let constructor_by_id = |id: Option<i64>| {
+        season
+            .constructors
+            .iter()
+            .position(|c| Some(c.fantasy_id as i64) == id)
+    };
+
+    let final_fix = if flag(team, "isfinalfix") {
+        let driver = |key| {
+            driver_by_id(number(team, key))
+                .ok_or_else(|| format_error(&format!("unknown driver in {key}")))
+        };
+        Some((driver("finalfxoldplayerid")?, driver("finalfxnewplayerid")?))
+    } else {
+        None
+    };
+
+    let mut picked = Team::new();
+    let mut drs_driver = None;
+    let mut extra_drs = None;
+    for player in players {
+        let id = number(player, "id");
+        if let Some(driver) = driver_by_id(id) {
+            let driver = match final_fix {
+                Some((out, sub)) if sub == driver => out,
+                _ => driver,
+            };
+            picked = picked.set_driver(driver);
+            if flag(player, "iscaptain") {
+                drs_driver = Some(driver);
+            }
+            if flag(player, "ismgcaptain") {
+                extra_drs = Some(driver);
+            }
+        } else if let Some(constructor) = constructor_by_id(id) {
+            picked = picked.set_constructor(constructor);
+        } else {
+            return Err(format_error(&format!(
+                "unknown player id {}",
+                id.unwrap_or_default()
+            )));
+        }
+    }
+
+    let chip = if let Some((out, sub)) = final_fix {
+        Some(Chip::FinalFix(out, sub))
+    } else if let Some(driver) = extra_drs.filter(|_| flag(team, "isextradrs")) {
+        Some(Chip::ExtraDRS(driver))
+    } else if flag(team, "islimitless") {
+        Some(Chip::Limitless)
+    } else if flag(team, "iswildcard") {
+        Some(Chip::Wildcard)
+    } else if flag(team, "isautopilot") {
+        Some(Chip::AutoPilot)
+    } else if flag(team, "isnonegative") {
+        Some(Chip::NoNegative)
+    } else {
+        None
+    };
+
+    Ok(ExtendedTeam {
+        team: picked,
+        chip,
+        drs_driver: drs_driver
+            .or(extra_drs)
+            .ok_or_else(|| format_error("team without DRS driver"))?,
+        negative: number(team, "transferpenalty").unwrap_or_default().abs() as isize,
+    })
+}
+
+/// Fetches the teams of every member of a private league for the first
+/// `weeks` gamedays.
+pub fn fetch_league(
+    feed: &mut dyn Feed,
+    season: &Season,
+    league_id: usize,
+    weeks: usize,
+) -> Result<Vec<(LeagueMember, Vec<ExtendedTeam>)>> {
+    let members = parse_league_members(&feed.get(&league_members_path(league_id))?)?;
+    let mut league = Vec::new();
+    for member in members {
+        let mut teams = Vec::new();
+        for week in 0..weeks {
+            let input = feed.get(&member_team_path(&member, week))?;
+            let team = parse_member_team(season, &input).map_err(|err| {
+                Error::Fetch(format!("team {} in week {week}: {err}", member.team_name))
+            })?;
+            teams.push(team);
+        }
+        league.push((member, teams));
+    }
+    Ok(league)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{feed::ArchiveFeed, season::test_season};
+
+    fn fixture(file: &str) -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/league");
+        std::fs::read_to_string(path.join(file)).unwrap()
+    }
+
+    fn team(season: &Season, drivers: [&str; 5], constructors: [&str; 2]) -> u64 {
+        let mut team = Team::new();
+        for driver in drivers {
+            team = team.set_driver_name(season, driver).unwrap();
+        }
+        for constructor in constructors {
+            team = team.set_constructor_name(season, constructor).unwrap();
+        }
+        team.bitmap()
+    }
+
+    fn driver(season: &Season, name: &str) -> usize {
+        season.driver_index(name).unwrap()
+    }
+
+    #[test]
+    fn parses_league_members() {
+        let members = parse_league_members(&fixture("pvtleagueuserrankget_42.json")).unwrap();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].guid, "a1b2");
+        assert_eq!(members[0].team_no, 1);
+        assert_eq!(members[0].file_name(), "albon_ist_der_beste_angriff");
+        assert_eq!(members[1].team_no, 2);
+        assert_eq!(
+            member_team_path(&members[1], 0),
+            "opponentteam/opponentgamedayplayerteamget_c3d4_2_1.json"
+        );
+        assert!(parse_league_members(r#"{"Data": {"Value": {}}}"#).is_err());
+    }
+
+    #[test]
+    fn matches_members_to_existing_files() {
+        let member = |team_name: &str| LeagueMember {
+            team_name: team_name.to_string(),
+            guid: String::new(),
+            team_no: 1,
+        };
+        let existing = ["kai_gewinnteam".to_string(), "sky_f1_expertev2".to_string()];
+        let files = member_files(
+            &[
+                member("Sky F1 Experte-v2"),
+                member(" Kai  Gewinnteam "),
+                member("Neu!"),
+            ],
+            &existing,
+        )
+        .unwrap();
+        assert_eq!(files, ["sky_f1_expertev2", "kai_gewinnteam", "neu"]);
+
+        let clash = member_files(&[member("Max Tsunado"), member("max tsunado!")], &existing);
+        assert!(clash.is_err());
+    }
+
+    #[test]
+    fn parses_team_without_chip() {
+        let season = test_season();
+        let input = fixture("opponentgamedayplayerteamget_a1b2_1_1.json");
+        let team_week = parse_member_team(&season, &input).unwrap();
+        assert_eq!(
+            team_week.team.bitmap(),
+            team(
+                &season,
+                ["VER", "PER", "SAI", "LEC", "HAM"],
+                ["RedBull", "Ferrari"]
+            )
+        );
+        assert_eq!(team_week.drs_driver, driver(&season, "VER"));
+        assert_eq!(team_week.chip, None);
+        assert_eq!(team_week.negative, 0);
+    }
+
+    #[test]
+    fn stores_final_fix_with_replaced_driver() {
+        let season = test_season();
+        let input = fixture("opponentgamedayplayerteamget_a1b2_1_2.json");
+        let team_week = parse_member_team(&season, &input).unwrap();
+        // The line-up holds NOR, who came in for VER and took over DRS.
+        assert_eq!(
+            team_week.team.bitmap(),
+            team(
+                &season,
+                ["VER", "PER", "SAI", "LEC", "HAM"],
+                ["RedBull", "Ferrari"]
+            )
+        );
+        assert_eq!(team_week.drs_driver, driver(&season, "VER"));
+        assert_eq!(
+            team_week.chip,
+            Some(Chip::FinalFix(
+                driver(&season, "VER"),
+                driver(&season, "NOR")
+            ))
+        );
+        assert_eq!(team_week.negative, 10);
+    }
+
+    #[test]
+    fn parses_chip_flags() {
+        let season = test_season();
+        let extra_drs = parse_member_team(
+            &season,
+            &fixture("opponentgamedayplayerteamget_c3d4_2_1.json"),
+        )
+        .unwrap();
+        assert_eq!(extra_drs.drs_driver, driver(&season, "NOR"));
+        assert_eq!(extra_drs.chip, Some(Chip::ExtraDRS(driver(&season, "PIA"))));
+
+        let limitless = parse_member_team(
+            &season,
+            &fixture("opponentgamedayplayerteamget_c3d4_2_2.json"),
+        )
+        .unwrap();
+        assert_eq!(limitless.chip, Some(Chip::Limitless));
+        assert_eq!(limitless.drs_driver, driver(&season, "VER"));
+    }
+
+    #[test]
+    fn rejects_unknown_player() {
+        let season = test_season();
+        let input = r#"{"userTeam": [{"playerid": [{"id": "99999", "iscaptain": 1}]}]}"#;
+        assert!(parse_member_team(&season, input).is_err());
+    }
+
+    #[test]
+    fn fetches_league_from_recording() {
+        let season = test_season();
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/league");
+        let league = fetch_league(&mut ArchiveFeed::new(dir), &season, 42, 2).unwrap();
+        assert_eq!(league.len(), 2);
+        assert!(league.iter().all(|(_, teams)| teams.len() == 2));
+        assert_eq!(league[1].0.file_name(), "kai_gewinnteam");
+        assert_eq!(league[1].1[1].chip, Some(Chip::Limitless));
+    }
+}
@@ -0,0 +1,276 @@
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    data::{self, DataError, DataErrorKind},
+    error::{Error, Result},
+    events::{self, ScoringEvent},
+    season::Season,
+    week::{WeekCosts, WeekPoints},
+};
+
+/// New content of a data file, staged to be written together with the other
+/// files.
+pub struct FileUpdate {
+    pub file: PathBuf,
+    pub old: String,
+    pub new: String,
+}
+
+impl FileUpdate {
+    /// Stages `new` as the content of `file`.
+    pub fn new(file: PathBuf, new: String) -> Result<Self> {
+        Ok(Self {
+            old: read_existing(&file)?,
+            file,
+            new,
+        })
+    }
+
+    pub fn changed(&self) -> bool {
+        self.old != self.new
+    }
+}
+
+/// Collects fetched weeks as updates of the data files, so that any number of
+/// weeks is written in one go or not at all.
+pub struct Ingest<'a> {
+    season: &'a Season,
+    updates: Vec<FileUpdate>,
+    events: Option<Vec<ScoringEvent>>,
+}
+
+impl<'a> Ingest<'a> {
+    pub fn new(season: &'a Season) -> Self {
+        Self {
+            season,
+            updates: Vec::new(),
+            events: None,
+        }
+    }
+
+    /// Stages the points, costs and scoring events of `week` for every data
+    /// file. The rows of a week that is already in the files are replaced and
+    /// the week after the last one is appended. Weeks further ahead are
+    /// refused, as they would leave a gap.
+    pub fn add_week(
+        &mut self,
+        week: usize,
+        points: &WeekPoints,
+        costs: &WeekCosts,
+        events: &[ScoringEvent],
+    ) -> Result<()> {
+        let drivers = self.season.driver_names();
+        let constrs = self.season.constructor_names();
+        let driver_rows = [
+            (data::DRIVER_POINTS_FILE, &points.total.drivers),
+            (data::DRIVER_NEGATIVE_FILE, &points.negative.drivers),
+            (data::DRIVER_QUALI_FILE, &points.qualifying.drivers),
+            (
+                data::DRIVER_SPRINT_QUALI_FILE,
+                &points.sprint_qualifying.drivers,
+            ),
+            (data::DRIVER_SPRINT_FILE, &points.sprint.drivers),
+        ];
+        for (file, values) in driver_rows {
+            self.stage_row(file, &drivers, values, week, week)?;
+        }
+        self.stage_row(
+            data::CONSTRUCTOR_POINTS_FILE,
+            &constrs,
+            &points.total.constrs,
+            week,
+            week,
+        )?;
+        self.stage_row(
+            data::CONSTRUCTOR_NEGATIVE_FILE,
+            &constrs,
+            &points.negative.constrs,
+            week,
+            week,
+        )?;
+        // The costs files start with the prices before the first race, so the
+        // prices after a week go one row further down than its points.
+        self.stage_row(
+            data::DRIVER_COST_FILE,
+            &drivers,
+            &costs.drivers,
+            week + 1,
+            week,
+        )?;
+        self.stage_row(
+            data::CONSTRUCTOR_COST_FILE,
+            &constrs,
+            &costs.constrs,
+            week + 1,
+            week,
+        )?;
+        self.stage_events(week, events)
+    }
+
+    /// Whether the costs files have a row yet. The first row holds the prices
+    /// before the first race, which have to be staged with
+    /// [`Ingest::add_initial_prices`] before the first week.
+    pub fn has_prices(&mut self) -> Result<bool> {
+        Ok(self.update(data::DRIVER_COST_FILE)?.new.lines().count() > 1)
+    }
+
+    /// Stages the prices before the first race as the first row of the costs
+    /// files.
+    pub fn add_initial_prices(&mut self, costs: &WeekCosts) -> Result<()> {
+        let drivers = self.season.driver_names();
+        let constrs = self.season.constructor_names();
+        self.stage_row(data::DRIVER_COST_FILE, &drivers, &costs.drivers, 0, 0)?;
+        self.stage_row(data::CONSTRUCTOR_COST_FILE, &constrs, &costs.constrs, 0, 0)
+    }
+
+    pub fn changed(&self) -> bool {
+        self.updates.iter().any(FileUpdate::changed)
+    }
+
+    pub fn print_diff(&self) {
+        print_diff(&self.updates);
+    }
+
+    /// Writes all changed files, see [`data::replace_files`].
+    pub fn commit(self) -> Result<()> {
+        let files = self
+            .updates
+            .into_iter()
+            .filter(FileUpdate::changed)
+            .map(|update| (update.file, update.new))
+            .collect::<Vec<_>>();
+        data::replace_files(&files)
+    }
+
+    /// Returns the staged update of a file, starting from its current
+    /// content the first time.
+    fn update(&mut self, file: &str) -> Result<&mut FileUpdate> {
+        let path = self.season.data_file(file);
+        let index = match self.updates.iter().position(|u| u.file == path) {
+            Some(index) => index,
+            None => {
+                let old = read_existing(&path)?;
+                self.updates.push(FileUpdate {
+                    file: path,
+                    new: old.clone(),
+                    old,
+                });
+                self.updates.len() - 1
+            }
+        };
+        Ok(&mut self.updates[index])
+    }
+
+    /// Stages `values` as data row `row` of a file with one column per name
+    /// in `names`, ordering the values like the file's header. A missing file
+    /// is created with the names as header.
+    fn stage_row<D: Display>(
+        &mut self,
+        file: &str,
+        names: &[&str],
+        values: &[D],
+        row: usize,
+        week: usize,
+    ) -> Result<()> {
+        let update = self.update(file)?;
+        let path = update.file.as_path();
+        let mut lines = update.new.lines().map(str::to_string).collect::<Vec<_>>();
+        if lines.is_empty() {
+            lines.push(names.join(","));
+        }
+
+        let header = lines[0].split(',').collect::<Vec<_>>();
+        if let Some(missing) = names.iter().find(|name| !header.contains(name)) {
+            return Err(DataError::new(path, DataErrorKind::MissingColumn)
+                .at(Some(1), missing)
+                .into());
+        }
+        let ordered = header
+            .iter()
+            .map(|&column| {
+                names
+                    .iter()
+                    .position(|&name| name == column)
+                    .map(|index| values[index].to_string())
+                    .ok_or_else(|| {
+                        DataError::new(path, DataErrorKind::UnknownColumn).at(Some(1), column)
+                    })
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?
+            .join(",");
+
+        let rows = lines.len() - 1;
+        match row.cmp(&rows) {
+            Ordering::Less => lines[row + 1] = ordered,
+            Ordering::Equal => lines.push(ordered),
+            Ordering::Greater => {
+                return Err(Error::Argument(format!(
+                    "can't write week {week}: {} only has {rows} data rows, fetch the weeks before it first",
+                    path.display()
+                )))
+            }
+        }
+        update.new = lines.join("\n");
+        update.new.push('\n');
+        Ok(())
+    }
+
+    /// Stages the events file with the events of `week` replaced by `events`.
+    fn stage_events(&mut self, week: usize, events: &[ScoringEvent]) -> Result<()> {
+        let all = match &mut self.events {
+            Some(all) => all,
+            None => {
+                let existing = if self.season.data_file(events::EVENTS_FILE).exists() {
+                    events::read_events(self.season)?
+                } else {
+                    Vec::new()
+                };
+                self.events.insert(existing)
+            }
+        };
+        all.retain(|e| e.week != week);
+        all.extend_from_slice(events);
+        all.sort_by_key(|e| e.week);
+        let content = events::events_csv(self.season, all)?;
+        self.update(events::EVENTS_FILE)?.new = content;
+        Ok(())
+    }
+}
+
+/// Prints the lines each update changes, in the style of a unified diff.
+pub fn print_diff(updates: &[FileUpdate]) {
+    for update in updates.iter().filter(|u| u.changed()) {
+        let old = update.old.lines().collect::<Vec<_>>();
+        let new = update.new.lines().collect::<Vec<_>>();
+        let prefix = old.iter().zip(&new).take_while(|(o, n)| o == n).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(o, n)| o == n)
+            .count();
+        println!("--- {}", update.file.display());
+        println!("+++ {}", update.file.display());
+        println!("@@ line {} @@", prefix + 1);
+        for line in &old[prefix..old.len() - suffix] {
+            println!("-{line}");
+        }
+        for line in &new[prefix..new.len() - suffix] {
+            println!("+{line}");
+        }
+    }
+}
+
+/// Returns the content of a file, or nothing if it doesn't exist yet.
+fn read_existing(file: &Path) -> Result<String> {
+    match std::fs::read_to_string(file) {
+        Ok(content) => Ok(content),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(String::new()),
+        Err(err) => Err(Error::Io(file.to_path_buf(), err)),
+    }
+}
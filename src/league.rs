@@ -1,28 +1,47 @@
 use crate::{
-    data::player_data,
+    data::{self, player_data, DataError, DataErrorKind},
+    error::Result,
+    ledger::Ledger,
+    scoring::{self, Pick},
+    season::Season,
     team::{Chip, ExtendedTeam, TeamEnumeration},
-    week::{self, cost_of_team, WeekCosts, WeekPoints},
+    transfers::{self, TransferPolicy, WeekTransfers},
+    week::{self, WeekCosts, WeekPoints},
 };
 
-pub struct League<const Size: usize> {
-    pub teams: Vec<[ExtendedTeam; Size]>,
-    pub names: [String; Size],
+/// Teams of the league members, by week and then by member.
+pub struct League {
+    pub teams: Vec<Vec<ExtendedTeam>>,
+    pub names: Vec<String>,
+    pub policy: TransferPolicy,
 }
 
-impl<const Size: usize> League<Size> {
-    pub fn from_names(names: &[&str; Size]) -> Self {
-        let single_teams = names.map(player_data);
-        let teams = (0..single_teams[0].len())
-            .map(|week| std::array::from_fn(|i| single_teams[i][week].clone()))
+impl League {
+    /// Loads every member with a file in the league directory, in the order
+    /// of their names.
+    pub fn load(season: &Season) -> Result<Self> {
+        let names = data::league_members(season)?;
+        Self::from_names(season, &names)
+    }
+
+    pub fn from_names(season: &Season, names: &[String]) -> Result<Self> {
+        let single_teams = names
+            .iter()
+            .map(|name| player_data(season, name))
+            .collect::<Result<Vec<_>>>()?;
+        let weeks = single_teams.iter().map(Vec::len).min().unwrap_or_default();
+        let teams = (0..weeks)
+            .map(|week| single_teams.iter().map(|t| t[week].clone()).collect())
             .collect();
-        League {
+        Ok(League {
             teams,
-            names: names.map(str::to_string),
-        }
+            names: names.to_vec(),
+            policy: season.transfers.clone(),
+        })
     }
 
-    pub const fn size() -> usize {
-        Size
+    pub fn size(&self) -> usize {
+        self.names.len()
     }
 
     pub fn calculate_points_accumulated(
@@ -44,72 +63,89 @@ impl<const Size: usize> League<Size> {
         team: usize,
         week_points: &[WeekPoints],
     ) -> isize {
-        let mut points = 0;
         let t = &self.teams[week][team];
-        points = week::points_of_ext_team(&t, &week_points[week]);
-        if !matches!(t.chip, Some(Chip::Wildcard)) {
+        let mut points = scoring::score_ext_team(t, &week_points[week]).total();
+        if !self.policy.waives(t.chip.as_ref()) {
             points -= t.negative;
         }
         points
     }
 
-    pub fn calculate_budget(&self, week: usize, team: usize, week_costs: &[WeekCosts]) -> f32 {
-        let mut budget: f32 = 100.0;
-        for i in 0..=week {
-            if matches!(self.teams[i][team].chip, Some(Chip::Limitless)) {
-                if i > 0 {
-                    budget -= cost_of_team(self.teams[i - 1][team].team, &week_costs[i]);
-                    budget += cost_of_team(self.teams[i - 1][team].team, &week_costs[i + 1]);
-                }
-            } else {
-                budget -= cost_of_team(self.teams[i][team].team, &week_costs[i]);
-                budget += cost_of_team(self.teams[i][team].team, &week_costs[i + 1]);
-            }
-        }
-        budget
+    /// Teams a single player picked, one per week.
+    fn team_weeks(&self, team: usize) -> Vec<ExtendedTeam> {
+        self.teams.iter().map(|week| week[team].clone()).collect()
     }
 
-    pub fn points_for_all(&self, week_points: &[WeekPoints]) -> Vec<[isize; Size]> {
+    /// Purchases, sales and budget of a team over the weeks of the league.
+    pub fn ledger(&self, team: usize, week_costs: &[WeekCosts]) -> Result<Ledger> {
+        Ledger::new(&self.team_weeks(team), week_costs)
+    }
+
+    /// Transfers and penalties of a team derived from its consecutive teams.
+    pub fn transfer_history(&self, team: usize) -> Vec<WeekTransfers> {
+        transfers::transfer_history(&self.policy, &self.team_weeks(team))
+    }
+
+    pub fn points_for_all(&self, week_points: &[WeekPoints]) -> Vec<Vec<isize>> {
         let mut result = Vec::new();
-        for (week_index, week_teams) in self.teams.iter().enumerate() {
-            let points = std::array::from_fn(|i| {
-                self.calculate_points_accumulated(week_index, i, week_points)
-            });
+        for week_index in 0..self.teams.len() {
+            let points = (0..self.size())
+                .map(|i| self.calculate_points_accumulated(week_index, i, week_points))
+                .collect();
             result.push(points)
         }
         result
     }
 
-    pub fn distance_to_first(&self, week_points: &[WeekPoints]) -> Vec<[usize; Size]> {
+    pub fn distance_to_first(&self, week_points: &[WeekPoints]) -> Vec<Vec<usize>> {
         let mut result = Vec::new();
         let ps = self.points_for_all(week_points);
         for points in ps.into_iter() {
-            let max = points.iter().max().unwrap();
-            result.push(points.map(|x| (max - x) as usize));
+            let max = points.iter().max().copied().unwrap_or_default();
+            result.push(points.iter().map(|x| (max - x) as usize).collect());
         }
         result
     }
 
+    /// Best result a team could have scored in a week with its chip, knowing
+    /// the points in advance. Unlike a forecast the DRS driver is picked in
+    /// hindsight as well.
     pub fn optimal_result(
         &self,
+        season: &Season,
         team: usize,
         week: usize,
         week_points: &[WeekPoints],
         week_costs: &[WeekCosts],
-    ) -> isize {
-        let last_week_budget = if week == 0 {
-            100.0
-        } else {
-            self.calculate_budget(week - 1, team, week_costs)
-        };
+    ) -> Result<isize> {
+        let last_week_budget = self.ledger(team, week_costs)?.budget(week.checked_sub(1));
         let chip = self.teams[week][team].chip.as_ref();
-        TeamEnumeration::new()
+        let teams = self.team_weeks(team);
+        let previous = transfers::previous_team(&teams, week).filter(|_| !self.policy.waives(chip));
+        let free = transfers::transfer_history(&self.policy, &teams)[week].free;
+        let rule = scoring::hindsight_rule(chip);
+        TeamEnumeration::new(season, week)
             .filter(|&t| {
                 chip == Some(&Chip::Limitless)
                     || week::cost_of_team(t, &week_costs[week]) <= last_week_budget
             })
-            .map(|t| week::points_of_team_chip(t, &week_points[week], chip))
+            .map(|t| {
+                let penalty = previous.map_or(0, |p| {
+                    self.policy
+                        .penalty(transfers::transfers_between(p, t), free)
+                });
+                let score =
+                    scoring::score_team(rule.as_ref(), t, Pick::Hindsight, &week_points[week]);
+                score.total() - penalty
+            })
             .max()
-            .unwrap()
+            .ok_or_else(|| {
+                let message = format!(
+                    "no team fits the budget of {last_week_budget:.1} in {}",
+                    season.races[week].name
+                );
+                let file = data::player_file(season, &self.names[team]);
+                DataError::new(&file, DataErrorKind::Inconsistent(message)).into()
+            })
     }
 }
@@ -0,0 +1,247 @@
+use std::path::Path;
+
+use crate::{
+    data::{self, DataError, DataErrorKind},
+    error::Result,
+    events::Entity,
+    team::{Chip, ExtendedTeam},
+    week::WeekCosts,
+};
+
+/// Budget every team starts the season with.
+pub const STARTING_BUDGET: f32 = 100.0;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TransactionKind {
+    Buy,
+    Sell,
+}
+
+/// A driver or constructor bought or sold before a race, at the price going
+/// into it. Sales also record what was paid for the asset.
+#[derive(Clone, Copy, Debug)]
+pub struct Transaction {
+    pub week: usize,
+    pub entity: Entity,
+    pub kind: TransactionKind,
+    pub price: f32,
+    pub purchase_price: f32,
+}
+
+/// An asset of the team, with the price it was bought at.
+#[derive(Clone, Copy, Debug)]
+pub struct Holding {
+    pub entity: Entity,
+    pub purchase_price: f32,
+}
+
+/// State of a team after a week's race, with the prices after the race.
+#[derive(Clone, Debug)]
+pub struct WeekBalance {
+    pub holdings: Vec<Holding>,
+    pub cash: f32,
+    pub team_value: f32,
+}
+
+impl WeekBalance {
+    /// Cost cap for the next week: the cash in the bank plus what the team
+    /// could be sold for.
+    pub fn budget(&self) -> f32 {
+        self.cash + self.team_value
+    }
+}
+
+/// Every purchase and sale of a team over the season and its balance after
+/// each week. Assets are bought and sold at the price going into a race, so
+/// the budget grows or shrinks with the prices of the assets held.
+#[derive(Clone, Debug)]
+pub struct Ledger {
+    pub transactions: Vec<Transaction>,
+    pub weeks: Vec<WeekBalance>,
+}
+
+fn price(costs: &WeekCosts, entity: Entity) -> f32 {
+    match entity {
+        Entity::Driver(d) => costs.drivers[d],
+        Entity::Constructor(c) => costs.constrs[c],
+    }
+}
+
+fn entities(team: &ExtendedTeam) -> Vec<Entity> {
+    let drivers = team.team.drivers().into_iter().map(Entity::Driver);
+    let constrs = team
+        .team
+        .constructors()
+        .into_iter()
+        .map(Entity::Constructor);
+    drivers.chain(constrs).collect()
+}
+
+impl Ledger {
+    /// Replays the weekly teams of a player. `costs` needs one more entry than
+    /// `teams`, the prices after the last week.
+    pub fn new(teams: &[ExtendedTeam], costs: &[WeekCosts]) -> Result<Self> {
+        if costs.len() <= teams.len() {
+            let message = format!(
+                "has {} rows, but the teams of {} weeks need {}",
+                costs.len(),
+                teams.len(),
+                teams.len() + 1
+            );
+            let file = Path::new(data::DRIVER_COST_FILE);
+            return Err(DataError::new(file, DataErrorKind::Inconsistent(message)).into());
+        }
+        let mut ledger = Ledger {
+            transactions: Vec::new(),
+            weeks: Vec::with_capacity(teams.len()),
+        };
+        let mut holdings: Vec<Holding> = Vec::new();
+        let mut cash = STARTING_BUDGET;
+
+        for (week, team) in teams.iter().enumerate() {
+            let prices = &costs[week];
+            // With Limitless the week's team doesn't touch the budget: it is
+            // picked on top of the saved team, which comes back unchanged
+            // afterwards, so nothing is bought or sold.
+            if !matches!(team.chip, Some(Chip::Limitless)) {
+                let mut wanted = entities(team);
+                // The Final Fix swaps a driver during the race weekend, at the
+                // same prices as the other transfers.
+                if let Some(Chip::FinalFix(out, sub)) = team.chip {
+                    wanted.retain(|&e| e != Entity::Driver(out));
+                    wanted.push(Entity::Driver(sub));
+                }
+
+                let (kept, sold): (Vec<_>, Vec<_>) = holdings
+                    .into_iter()
+                    .partition(|h| wanted.contains(&h.entity));
+                for holding in sold {
+                    let price = price(prices, holding.entity);
+                    cash += price;
+                    ledger.transactions.push(Transaction {
+                        week,
+                        entity: holding.entity,
+                        kind: TransactionKind::Sell,
+                        price,
+                        purchase_price: holding.purchase_price,
+                    });
+                }
+                holdings = kept;
+                for entity in wanted {
+                    if holdings.iter().any(|h| h.entity == entity) {
+                        continue;
+                    }
+                    let price = price(prices, entity);
+                    cash -= price;
+                    holdings.push(Holding {
+                        entity,
+                        purchase_price: price,
+                    });
+                    ledger.transactions.push(Transaction {
+                        week,
+                        entity,
+                        kind: TransactionKind::Buy,
+                        price,
+                        purchase_price: price,
+                    });
+                }
+            }
+
+            let team_value = holdings
+                .iter()
+                .map(|h| price(&costs[week + 1], h.entity))
+                .sum();
+            ledger.weeks.push(WeekBalance {
+                holdings: holdings.clone(),
+                cash,
+                team_value,
+            });
+        }
+        Ok(ledger)
+    }
+
+    /// Budget after `week`, or the starting budget before the first week.
+    pub fn budget(&self, week: Option<usize>) -> f32 {
+        week.map_or(STARTING_BUDGET, |week| self.weeks[week].budget())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{team::Team, week::Grid};
+
+    fn team(drivers: [usize; 5], chip: Option<Chip>) -> ExtendedTeam {
+        let team = drivers.into_iter().fold(Team::new(), Team::set_driver);
+        ExtendedTeam {
+            team: team.set_constructor(0).set_constructor(1),
+            chip,
+            drs_driver: drivers[0],
+            negative: 0,
+        }
+    }
+
+    /// Prices of seven drivers and two constructors, all 10 except for
+    /// driver 0.
+    fn costs(driver_0: f32) -> WeekCosts {
+        let mut costs = Grid {
+            drivers: vec![10.0; 7],
+            constrs: vec![10.0; 2],
+        };
+        costs.drivers[0] = driver_0;
+        costs
+    }
+
+    #[test]
+    fn budget_follows_prices_of_assets_held() {
+        let teams = [team([0, 1, 2, 3, 4], None), team([0, 1, 2, 3, 5], None)];
+        let ledger = Ledger::new(&teams, &[costs(10.0), costs(12.0), costs(13.0)]).unwrap();
+
+        assert_eq!(ledger.budget(None), STARTING_BUDGET);
+        assert_eq!(ledger.budget(Some(0)), 102.0);
+        assert_eq!(ledger.budget(Some(1)), 103.0);
+        let week_1 = ledger
+            .transactions
+            .iter()
+            .filter(|t| t.week == 1)
+            .map(|t| (t.kind, t.entity))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            week_1,
+            [
+                (TransactionKind::Sell, Entity::Driver(4)),
+                (TransactionKind::Buy, Entity::Driver(5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn limitless_leaves_budget_alone() {
+        let teams = [
+            team([0, 1, 2, 3, 4], None),
+            team([2, 3, 4, 5, 6], Some(Chip::Limitless)),
+        ];
+        let ledger = Ledger::new(&teams, &[costs(10.0), costs(12.0), costs(13.0)]).unwrap();
+
+        assert!(ledger.transactions.iter().all(|t| t.week == 0));
+        assert_eq!(ledger.weeks[1].holdings.len(), 7);
+        assert_eq!(ledger.budget(Some(1)), 103.0);
+    }
+
+    #[test]
+    fn final_fix_sells_replaced_driver() {
+        let teams = [team([0, 1, 2, 3, 4], Some(Chip::FinalFix(0, 5)))];
+        let ledger = Ledger::new(&teams, &[costs(12.0), costs(20.0)]).unwrap();
+
+        let holds = |entity| ledger.weeks[0].holdings.iter().any(|h| h.entity == entity);
+        assert!(!holds(Entity::Driver(0)));
+        assert!(holds(Entity::Driver(5)));
+        assert_eq!(ledger.budget(Some(0)), 100.0);
+    }
+
+    #[test]
+    fn needs_prices_after_last_week() {
+        let teams = [team([0, 1, 2, 3, 4], None)];
+        assert!(Ledger::new(&teams, &[costs(10.0)]).is_err());
+    }
+}
@@ -1,185 +1,832 @@
-use std::io::Write;
-use std::{fmt::Display, fs::OpenOptions};
+use std::fs::File;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::Parser;
+use cli::{
+    Cli, Command, FeedOptions, Fetch, Format, LeagueCommand, Optimize, Plan, Render, RetryOptions,
+    WeekOption,
+};
 
 use data::{costs, points};
+use error::{Error, Result};
+use events::Entity;
+use feed::Feed;
+use ingest::Ingest;
 use league::League;
-use team::{Team, TeamEnumeration};
+use optimizer::Candidate;
+use player_ids::IdMatch;
+use season::Season;
+use team::Team;
 use week::{WeekCosts, WeekPoints};
 
+pub mod cli;
 pub mod data;
+pub mod error;
+pub mod events;
+pub mod feed;
 pub mod fetch_data;
+pub mod fetch_league;
+pub mod ingest;
 pub mod league;
+pub mod ledger;
+pub mod optimizer;
+pub mod planner;
+pub mod player_ids;
+pub mod prices;
 pub mod render;
+pub mod scoring;
+pub mod season;
 pub mod team;
+pub mod transfers;
+pub mod validate;
 pub mod week;
 
-const RACES: [&str; 24] = [
-    "Bahrain",
-    "Saudi Arabia",
-    "Australia",
-    "Japan",
-    "China",
-    "Miami",
-    "Imola",
-    "Monaco",
-    "Canada",
-    "Spain",
-    "Austria",
-    "Great Britan",
-    "Hungary",
-    "Belgium",
-    "Netherlands",
-    "Monza",
-    "Azerbaijan",
-    "Singapore",
-    "Texas",
-    "Mexico",
-    "Brazil",
-    "Las Vegas",
-    "Qatar",
-    "Abu Dhabi",
-];
-
-fn main() {
-    let overview = std::env::args().find(|a| a == "--overview" || a == "--graphics").is_some();
-    let chart = std::env::args().find(|a| a == "--chart" || a == "--graphics").is_some();
-    let fetch_week = std::env::args().enumerate().find(|(_, a)| a == "--data").map(|(i, _)| std::env::args().nth(i + 1).expect("missing week argument!"));
-
-    if let Some(week) = fetch_week {
-        let w = week.parse().expect("invalid week");
-        println!("Fetch data for {}", RACES[w]);
-        scrape_new_data(w);
-    }
-    if overview {
-        println!("Render overview");
-        render_league_overview();
-    }
-    if chart {
-        println!("Render chart");
-        render_point_chart();
-    }
-}
-
-fn render_league_overview() {
-    let p = points();
-    let c = costs();
-    let names = [
-        "albon_ist_der_beste_angriff",
-        "kai_gewinnteam",
-        "max_tsunado",
-        "reiswaffel_racing",
-        "sky_f1_experte_v2",
-        "smoooothdrivers",
-        "verstappen_verdoppeln_lol",
-    ];
-    let league = League::from_names(&names);
-    let file = std::fs::File::create("overview.svg").unwrap();
-    render::render_league_overview(&league, &p, &c, file);
-}
-
-fn render_point_chart() {
-    let p = points();
-    let names = [
-        "albon_ist_der_beste_angriff",
-        "kai_gewinnteam",
-        "max_tsunado",
-        "reiswaffel_racing",
-        "sky_f1_experte_v2",
-        "smoooothdrivers",
-        "verstappen_verdoppeln_lol",
-    ];
-    let league = League::from_names(&names);
-    let file = std::fs::File::create("distance_to_first.svg").unwrap();
-    render::render_chart(&league, &p, file);
-}
-
-fn scrape_new_data(week: usize) {
-    let (p, c) = fetch_data::fetch_data(week).unwrap();
-
-    append(&to_csv_line(&c.drivers), data::DRIVER_COST_FILE);
-    append(&to_csv_line(&c.constrs), data::CONSTRUCTOR_COST_FILE);
-    append(&to_csv_line(&p.drivers), data::DRIVER_POINTS_FILE);
-    append(&to_csv_line(&p.drivers_negative), data::DRIVER_NEGATIVE_FILE);
-    append(&to_csv_line(&p.drivers_qualifying), data::DRIVER_QUALI_FILE);
-    append(&to_csv_line(&p.constrs), data::CONSTRUCTOR_POINTS_FILE);
-    append(&to_csv_line(&p.constrs_negative), data::CONSTRUCTOR_NEGATIVE_FILE);
-}
-
-fn append(row: &str, file: &str) {
-    let mut file = OpenOptions::new()
-        .write(true)
-        .append(true)
-        .open(file)
-        .unwrap();
-    writeln!(file, "{}", row).unwrap();
-}
-
-fn to_csv_line<D: Display>(row: &[D]) -> String {
-    row.iter()
-        .map(|v| v.to_string())
-        .collect::<Vec<_>>()
-        .join(",")
-}
-
-fn query_best_teams<F>(budget: f32, eval: F)
-where
-    F: Fn(Team, &[WeekPoints], &[WeekCosts]) -> f32,
-{
-    let points = data::points();
-    let costs = data::costs();
-
-    let all_teams = TeamEnumeration::new();
-    let mut pt = all_teams
-        .filter(|&team| week::cost_of_team(team, &costs.last().unwrap()) <= budget)
-        .map(|team| {
-            let p = eval(team, &points, &costs);
-            (p, team)
-        })
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let season_file = cli.season.unwrap_or_else(|| {
+        let dir = cli.data_dir.as_deref().unwrap_or(season::DATA_DIR.as_ref());
+        dir.join(season::SEASON_FILE)
+    });
+    let mut season = Season::load(&season_file)?;
+    if let Some(dir) = cli.data_dir {
+        season.data_dir = dir;
+    }
+
+    match cli.command {
+        Command::Fetch(Fetch::Weeks {
+            weeks,
+            backfill,
+            resume,
+            feed,
+            output,
+        }) => {
+            let selection = match weeks {
+                Some(weeks) if !backfill => parse_weeks(&season, &weeks)?,
+                _ => WeekSelection::Missing,
+            };
+            scrape_new_data(&season, selection, &feed, resume, output.dry_run)
+        }
+        Command::Fetch(Fetch::Ids {
+            player_list,
+            feed,
+            output,
+        }) => discover_fantasy_ids(
+            &season,
+            &season_file,
+            player_list.as_deref(),
+            &feed,
+            output.dry_run,
+        ),
+        Command::Fetch(Fetch::League {
+            league_id,
+            archive,
+            cookie,
+            services_url,
+            retry,
+            output,
+        }) => {
+            let mut feed: Box<dyn Feed> = match archive {
+                Some(dir) => Box::new(feed::ArchiveFeed::new(dir)),
+                None => {
+                    let cookie = cookie.ok_or_else(|| {
+                        Error::Argument(
+                            "fetching a league needs --cookie or F1_FANTASY_COOKIE".to_string(),
+                        )
+                    })?;
+                    let http = feed::HttpFeed::new(&services_url).with_cookie(cookie);
+                    Box::new(feed::ArchivingFeed::new(
+                        retrying(http, &retry),
+                        feed::todays_archive(&season.data_dir),
+                        false,
+                    )?)
+                }
+            };
+            fetch_league_teams(&season, &mut feed, league_id, output.dry_run)
+        }
+        Command::Fetch(Fetch::Import { dir }) => import_archive_data(&season, dir),
+        Command::Render(Render::Overview { week, output }) => {
+            render_league_overview(&season, week, &output)
+        }
+        Command::Render(Render::Chart { output }) => render_point_chart(&season, &output),
+        Command::Optimize(options) => optimize(&season, options),
+        Command::Plan(options) => plan_races(&season, options),
+        Command::Validate => validate_data(&season),
+        Command::League(LeagueCommand::Standings { week }) => print_standings(&season, week),
+        Command::Events => print_event_summary(&season),
+        Command::Prices => print_price_report(&season),
+    }
+}
+
+/// Resolves the week selected with `--week`, a week index or race name, to a
+/// week the league has teams for. Defaults to the latest one.
+fn league_week(season: &Season, league: &League, week: WeekOption) -> Result<usize> {
+    let weeks = league.teams.len();
+    let Some(value) = week.week else {
+        return weeks
+            .checked_sub(1)
+            .ok_or_else(|| Error::Argument("the league has no teams with points yet".to_string()));
+    };
+    let week = parse_week(season, &value)?;
+    if week >= weeks {
+        return Err(Error::Argument(format!(
+            "the league only has teams with points for {weeks} week(s)"
+        )));
+    }
+    Ok(week)
+}
+
+/// Loads the league with the teams of the weeks that have points. Teams are
+/// often entered before the points of their race are fetched.
+fn scored_league(season: &Season, points: &[WeekPoints]) -> Result<League> {
+    let mut league = League::load(season)?;
+    league.teams.truncate(points.len());
+    Ok(league)
+}
+
+fn print_event_summary(season: &Season) -> Result<()> {
+    let events = events::read_events(season)?;
+    for (event, points) in events::totals_by_event(&events) {
+        println!("{event:<40} {points:>6}");
+    }
+    Ok(())
+}
+
+/// Prints the predicted price changes after the latest week next to the
+/// actual ones, and how many predictions of the whole season were right,
+/// exactly or at least in whether the price went up.
+fn print_price_report(season: &Season) -> Result<()> {
+    let p = points(season)?;
+    let c = costs(season)?;
+    let weeks = p.len().min(c.len().saturating_sub(1));
+    let rules = season.price_changes.as_ref().ok_or_else(|| {
+        Error::Argument("the season file has no [price_changes] to predict with".to_string())
+    })?;
+
+    let mut right = 0;
+    let mut direction = 0;
+    let mut total = 0;
+    for week in 0..weeks {
+        let predictions = rules.predict(&p, &c, week);
+        let actual = |price: fn(&WeekCosts) -> &[f32], i: usize| {
+            prices::round_change(price(&c[week + 1])[i] - price(&c[week])[i])
+        };
+        let rows = predictions
+            .drivers
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (season.driver_name(i), *p, actual(|c| &c.drivers, i)))
+            .chain(
+                predictions
+                    .constrs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| (season.constructor_name(i), *p, actual(|c| &c.constrs, i))),
+            );
+
+        if week + 1 == weeks {
+            println!("Price changes after {}", season.races[week].name);
+        }
+        for (name, prediction, actual) in rows {
+            let Some(prediction) = prediction else {
+                continue;
+            };
+            let hit = prices::round_change(prediction.change) == actual;
+            total += 1;
+            right += usize::from(hit);
+            direction += usize::from((prediction.change > 0.0) == (actual > 0.0));
+            if week + 1 == weeks {
+                println!(
+                    "{name:<12} {:>5.1} {:>6.2} ppm  predicted {:>+5.1}  actual {:>+5.1}{}",
+                    prediction.price,
+                    prediction.points_per_million,
+                    prediction.change,
+                    actual,
+                    if hit { "" } else { "  !" }
+                );
+            }
+        }
+    }
+    if total > 0 {
+        let percent = |count: usize| 100.0 * count as f32 / total as f32;
+        println!(
+            "{right} of {total} predictions right ({:.1}%), {direction} in the right direction ({:.1}%)",
+            percent(right),
+            percent(direction)
+        );
+    }
+    Ok(())
+}
+
+fn validate_data(season: &Season) -> Result<()> {
+    let report = validate::validate(season)?;
+    for warning in &report.warnings {
+        println!("warning: {warning}");
+    }
+    for problem in &report.problems {
+        println!("{problem}");
+    }
+    if report.problems.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Validation(report.problems.len()))
+    }
+}
+
+fn create_output(file: &Path) -> Result<File> {
+    File::create(file).map_err(|err| Error::Io(file.into(), err))
+}
+
+fn render_league_overview(season: &Season, week: WeekOption, output: &Path) -> Result<()> {
+    let p = points(season)?;
+    let c = costs(season)?;
+    let league = scored_league(season, &p)?;
+    let week = league_week(season, &league, week)?;
+    let file = create_output(output)?;
+    render::render_league_overview(season, &league, &p, &c, week, file)
+}
+
+fn render_point_chart(season: &Season, output: &Path) -> Result<()> {
+    let p = points(season)?;
+    let league = scored_league(season, &p)?;
+    let file = create_output(output)?;
+    render::render_chart(&league, &p, file)
+}
+
+/// Prints the members of the league ordered by their points after `week`,
+/// with the points, transfers and budget of that week.
+fn print_standings(season: &Season, week: WeekOption) -> Result<()> {
+    let p = points(season)?;
+    let c = costs(season)?;
+    let league = scored_league(season, &p)?;
+    let week = league_week(season, &league, week)?;
+    let points = &league.points_for_all(&p)[week];
+    let mut order = (0..league.size()).collect::<Vec<_>>();
+    order.sort_by_key(|&team| std::cmp::Reverse(points[team]));
+    let budgets = (0..league.size())
+        .map(|team| Ok(league.ledger(team, &c)?.budget(Some(week))))
+        .collect::<Result<Vec<_>>>()?;
+
+    println!("Standings after {}", season.races[week].name);
+    println!(
+        "{:>2}  {:<30} {:>6} {:>5} {:>9} {:>7} {:>7}",
+        "#", "Team", "Points", "Week", "Transfers", "Penalty", "Budget"
+    );
+    for (rank, team) in order.into_iter().enumerate() {
+        let transfers = league.transfer_history(team)[week];
+        println!(
+            "{:>2}  {:<30} {:>6} {:>5} {:>9} {:>7} {:>7.1}",
+            rank + 1,
+            league.names[team],
+            points[team],
+            league.calculate_points_week(week, team, &p),
+            transfers.transfers,
+            league.teams[week][team].negative,
+            budgets[team]
+        );
+    }
+    Ok(())
+}
+
+/// Opens the feed source selected on the command line: plain HTTP by default
+/// or a headless Chrome with `--chrome`, retrying failed requests.
+fn open_feed(options: &FeedOptions) -> Result<Box<dyn feed::Feed>> {
+    Ok(Box::new(retrying(open_source(options)?, &options.retry)))
+}
+
+fn retrying<F: Feed>(inner: F, options: &RetryOptions) -> feed::RetryingFeed<F> {
+    feed::RetryingFeed::new(
+        inner,
+        options.retries,
+        Duration::from_millis(options.backoff),
+        Duration::from_millis(options.request_interval),
+    )
+}
+
+fn open_source(options: &FeedOptions) -> Result<Box<dyn feed::Feed>> {
+    if options.chrome {
+        #[cfg(feature = "chrome")]
+        return Ok(Box::new(feed::ChromeFeed::new(&options.feed_url)?));
+        #[cfg(not(feature = "chrome"))]
+        return Err(Error::Argument(
+            "--chrome needs a build with the chrome feature".to_string(),
+        ));
+    }
+    Ok(Box::new(feed::HttpFeed::new(&options.feed_url)))
+}
+
+/// Fetches the teams of all members of a private league into the league
+/// files. With `dry_run`, only prints the changes.
+fn fetch_league_teams(
+    season: &Season,
+    feed: &mut dyn Feed,
+    league_id: usize,
+    dry_run: bool,
+) -> Result<()> {
+    let weeks = points(season)?.len();
+    let league = fetch_league::fetch_league(feed, season, league_id, weeks)?;
+    let existing = data::league_members(season)?;
+    let members = league
+        .iter()
+        .map(|(member, _)| member.clone())
         .collect::<Vec<_>>();
-    pt.sort_by(|(p1, _), (p2, _)| p1.total_cmp(p2).reverse());
-    for (p, t) in pt {
+    let names = fetch_league::member_files(&members, &existing)?;
+    let mut updates = Vec::new();
+    for ((member, teams), name) in league.iter().zip(&names) {
+        let file = data::player_file(season, name);
+        println!("{}: {}", member.team_name, file.display());
+        let content = data::player_file_content(season, teams);
+        updates.push(ingest::FileUpdate::new(file, content)?);
+    }
+    if dry_run {
+        ingest::print_diff(&updates);
+        Ok(())
+    } else {
+        let files = updates
+            .into_iter()
+            .filter(ingest::FileUpdate::changed)
+            .map(|update| (update.file, update.new))
+            .collect::<Vec<_>>();
+        let dir = season.data_file(data::LEAGUE_DIR);
+        std::fs::create_dir_all(&dir).map_err(|err| Error::Io(dir, err))?;
+        data::replace_files(&files)
+    }
+}
+
+fn import_archive_data(season: &Season, dir: PathBuf) -> Result<()> {
+    println!("Import data from {}", dir.display());
+    let mut feed = feed::ArchiveFeed::new(dir);
+    let (p, c, e) = fetch_data::fetch_season(&mut feed, season)?;
+    let mut files = data::data_files(season, &p, &c);
+    files.push((
+        season.data_file(events::EVENTS_FILE),
+        events::events_csv(season, &e)?,
+    ));
+    data::replace_files(&files)
+}
+
+/// Matches the roster against the game's player list, from the feed or from
+/// a saved copy in `player_list`, and writes the ids into the season file.
+/// With `dry_run`, only prints them.
+fn discover_fantasy_ids(
+    season: &Season,
+    season_file: &Path,
+    player_list: Option<&Path>,
+    options: &FeedOptions,
+    dry_run: bool,
+) -> Result<()> {
+    let input = match player_list {
+        Some(file) => std::fs::read_to_string(file).map_err(|err| Error::Io(file.into(), err))?,
+        None => {
+            let archive = feed::todays_archive(&season.data_dir);
+            let mut feed = feed::ArchivingFeed::new(open_feed(options)?, archive, false)?;
+            feed.get(player_ids::PLAYER_LIST_PATH)?
+        }
+    };
+    let players = player_ids::parse_player_list(&input)?;
+    let mapping = player_ids::match_ids(season, &players);
+
+    let entries = season
+        .drivers
+        .iter()
+        .map(|d| (&d.name, d.fantasy_id))
+        .zip(&mapping.drivers)
+        .chain(
+            season
+                .constructors
+                .iter()
+                .map(|c| (&c.name, c.fantasy_id))
+                .zip(&mapping.constructors),
+        );
+    for ((name, current), id_match) in entries {
+        match id_match {
+            IdMatch::Found(id) if *id == current => println!("{name:<12} {id}"),
+            IdMatch::Found(id) => println!("{name:<12} {id} (was {current})"),
+            IdMatch::Missing => println!("{name:<12} not found, keeping {current}"),
+            IdMatch::Ambiguous(candidates) => {
+                let candidates = candidates
+                    .iter()
+                    .map(|p| format!("{} {} ({})", p.id, p.name, p.team))
+                    .collect::<Vec<_>>();
+                println!(
+                    "{name:<12} ambiguous, keeping {current}: {}",
+                    candidates.join(", ")
+                );
+            }
+        }
+    }
+
+    if !dry_run {
+        player_ids::write_ids(season_file, &mapping)?;
+    }
+    match mapping.unmatched() {
+        0 => Ok(()),
+        count => Err(Error::UnmatchedIds(count)),
+    }
+}
+
+/// Weeks to fetch, selected by `fetch weeks`.
+enum WeekSelection {
+    Weeks(Range<usize>),
+    /// The last week the feed has complete data for.
+    Latest,
+    /// All complete weeks that aren't in the data files yet.
+    Missing,
+}
+
+/// Parses a week index or race name.
+fn parse_week(season: &Season, value: &str) -> Result<usize> {
+    value
+        .parse::<usize>()
+        .ok()
+        .or_else(|| {
+            season
+                .races
+                .iter()
+                .position(|race| race.name.eq_ignore_ascii_case(value))
+        })
+        .ok_or_else(|| Error::Argument(format!("invalid week {value:?}")))
+}
+
+/// Parses the weeks to fetch: a week index or race name, a range of weeks
+/// like `3..7` or `3..=7`, or `latest`.
+fn parse_weeks(season: &Season, value: &str) -> Result<WeekSelection> {
+    let week = |value: &str| parse_week(season, value);
+    if value == "latest" {
+        return Ok(WeekSelection::Latest);
+    }
+    let weeks = if let Some((start, end)) = value.split_once("..=") {
+        week(start)?..week(end)? + 1
+    } else if let Some((start, end)) = value.split_once("..") {
+        week(start)?..week(end)?
+    } else {
+        let week = week(value)?;
+        week..week + 1
+    };
+    if weeks.is_empty() {
+        return Err(Error::Argument(format!("no weeks in {value:?}")));
+    }
+    if weeks.end > season.races.len() {
+        return Err(Error::Argument(format!(
+            "week {} is not part of the season",
+            weeks.end - 1
+        )));
+    }
+    Ok(WeekSelection::Weeks(weeks))
+}
+
+/// Fetches the selected weeks through a single feed and writes them into the
+/// data files, replacing weeks that were fetched before. With `dry_run`, only
+/// prints the changes.
+fn scrape_new_data(
+    season: &Season,
+    selection: WeekSelection,
+    options: &FeedOptions,
+    resume: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let archive = feed::todays_archive(&season.data_dir);
+    let mut feed = feed::ArchivingFeed::new(open_feed(options)?, archive, resume)?;
+    let weeks = match selection {
+        WeekSelection::Weeks(weeks) => weeks,
+        WeekSelection::Latest => {
+            let completed = fetch_data::completed_weeks(&mut feed, season)?;
+            if completed == 0 {
+                return Err(Error::Fetch("no week is complete yet".to_string()));
+            }
+            completed - 1..completed
+        }
+        WeekSelection::Missing => {
+            let stored = points(season)?
+                .len()
+                .min(costs(season)?.len().saturating_sub(1));
+            stored..fetch_data::completed_weeks(&mut feed, season)?
+        }
+    };
+    if weeks.is_empty() {
+        println!("No missing weeks");
+        return Ok(());
+    }
+
+    let mut ingest = Ingest::new(season);
+    for week in weeks {
+        let race = season
+            .races
+            .get(week)
+            .ok_or_else(|| Error::Argument(format!("week {week} is not part of the season")))?;
+        println!("Fetch data for {}", race.name);
+        if week == 0 && !ingest.has_prices()? {
+            ingest.add_initial_prices(&fetch_data::initial_prices(&mut feed, season)?)?;
+        }
+        let (p, c, e) = fetch_data::fetch_data(&mut feed, season, week)?;
+        ingest.add_week(week, &p, &c, &e)?;
+    }
+    if dry_run {
+        ingest.print_diff();
+        if !ingest.changed() {
+            println!("No changes");
+        }
+        Ok(())
+    } else {
+        ingest.commit()
+    }
+}
+
+/// Lists the teams with the best forecast for the next race.
+fn optimize(season: &Season, options: Optimize) -> Result<()> {
+    let p = points(season)?;
+    let c = costs(season)?;
+    let (start, budget) = starting_team(season, options.from.as_deref(), options.budget, &c)?;
+    let query = optimizer::Query {
+        budget,
+        strategy: options.strategy,
+        top: options.top,
+        required: team_of_names(season, &options.require)?,
+        excluded: team_of_names(season, &options.exclude)?,
+        start,
+        max_transfers: options.max_transfers,
+    };
+    let candidates = optimizer::best_teams(season, &p, &c, &query);
+    print_candidates(season, &candidates, options.format)
+}
+
+/// Plans the teams for the next races and prints them with their transfers.
+fn plan_races(season: &Season, options: Plan) -> Result<()> {
+    let p = points(season)?;
+    let c = costs(season)?;
+    let first_week = p.len();
+    let projections = match &options.projections {
+        Some(file) => data::projections(season, file)?,
+        None => planner::project(season, options.strategy, &p, first_week, options.races),
+    };
+    if projections.is_empty() {
+        return Err(Error::Argument("no races to plan".to_string()));
+    }
+    if first_week + projections.len() > season.races.len() {
+        return Err(Error::Argument(format!(
+            "the season only has {} race(s) left",
+            season.races.len().saturating_sub(first_week)
+        )));
+    }
+    let (start, budget) = starting_team(season, options.from.as_deref(), options.budget, &c)?;
+    let limits = planner::Limits {
+        prices: c
+            .last()
+            .ok_or_else(|| Error::Argument("no prices yet".to_string()))?,
+        budget,
+        pool: options.pool,
+        max_transfers: options.max_transfers,
+    };
+    let plan = planner::plan(
+        season,
+        &season.transfers,
+        start.as_ref(),
+        first_week,
+        &projections,
+        &limits,
+    )
+    .ok_or_else(|| Error::Argument(format!("no team fits a budget of {budget:.1}")))?;
+
+    for week in &plan.weeks {
+        let team = week.team;
         println!(
-            "{} {:.2} \t {:.2}",
-            t,
-            p,
-            week::cost_of_team(t, &costs.last().unwrap())
+            "{:<16} {} {}  DRS {}",
+            season.races[week.week].name,
+            team.drivers().map(|d| season.driver_name(d)).join(" "),
+            team.constructors()
+                .map(|c| season.constructor_name(c))
+                .join(" "),
+            season.driver_name(week.drs_driver)
+        );
+        let transfers = week
+            .moves
+            .out
+            .iter()
+            .zip(&week.moves.into)
+            .map(|(&out, &into)| {
+                format!(
+                    "{} -> {}",
+                    entity_name(season, out),
+                    entity_name(season, into)
+                )
+            })
+            .collect::<Vec<_>>();
+        println!(
+            "{:<16} {:.2} points, {} transfer(s) with {} free, penalty {}{}{}",
+            "",
+            week.points,
+            transfers.len(),
+            week.free,
+            week.moves.penalty,
+            if transfers.is_empty() { "" } else { ": " },
+            transfers.join(", ")
         );
     }
+    println!("Total {:.2} points after penalties", plan.total());
+    Ok(())
 }
 
-fn unweighted_eval(team: Team, points: &[WeekPoints], _costs: &[WeekCosts]) -> f32 {
-    let mut akk_points = 0;
-    for wp in points {
-        akk_points += week::points_of_team(team, wp);
+fn entity_name(season: &Season, entity: Entity) -> &str {
+    match entity {
+        Entity::Driver(d) => season.driver_name(d),
+        Entity::Constructor(c) => season.constructor_name(c),
     }
-    akk_points as f32 / points.len() as f32
 }
 
-fn recency_weighted_eval<const S: usize, W: Weights<S>>(
-    team: Team,
-    points: &[WeekPoints],
-    _costs: &[WeekCosts],
-) -> f32 {
-    let mut avg_points = 0.0;
-    let weights = W::WEIGHTS;
-    for (w, p) in points.iter().enumerate() {
-        let s = std::cmp::min(points.len() - w - 1, weights.len() - 1);
-        avg_points += week::points_of_team(team, p) as f32 * weights[s];
+/// The team to start from, the current team of the league member `from` if
+/// given, and the budget: `budget` if given, else the member's budget or the
+/// starting budget without one.
+fn starting_team(
+    season: &Season,
+    from: Option<&str>,
+    budget: Option<f32>,
+    costs: &[WeekCosts],
+) -> Result<(Option<optimizer::Start>, f32)> {
+    match from {
+        Some(name) => {
+            let (start, member_budget) = current_team(season, name, costs)?;
+            Ok((Some(start), budget.unwrap_or(member_budget)))
+        }
+        None => Ok((None, budget.unwrap_or(ledger::STARTING_BUDGET))),
     }
-    avg_points
 }
 
-trait Weights<const S: usize> {
-    const SIZE: usize = S;
-    const WEIGHTS: [f32; S];
+/// The team a league member goes into the next race with, their free
+/// transfers and their budget at the latest prices.
+fn current_team(
+    season: &Season,
+    name: &str,
+    costs: &[WeekCosts],
+) -> Result<(optimizer::Start, f32)> {
+    let teams = data::player_data(season, name)?;
+    let Some(team) = transfers::previous_team(&teams, teams.len()) else {
+        return Err(Error::Argument(format!("{name} has no team yet")));
+    };
+    if costs.len() <= teams.len() {
+        return Err(Error::Argument(format!(
+            "{name} has a team for week {}, which has no prices yet",
+            teams.len() - 1
+        )));
+    }
+    let budget = ledger::Ledger::new(&teams, costs)?.budget(teams.len().checked_sub(1));
+    let start = optimizer::Start {
+        team,
+        free: transfers::free_transfers_after(&season.transfers, &teams),
+    };
+    Ok((start, budget))
 }
 
-struct LastWeek;
-impl Weights<2> for LastWeek {
-    const WEIGHTS: [f32; 2] = [1.0, 0.0];
+/// Collects drivers and constructors given by name into a team bitmap.
+fn team_of_names(season: &Season, names: &[String]) -> Result<Team> {
+    names.iter().try_fold(Team::new(), |team, name| {
+        if let Some(driver) = season.driver_index(name) {
+            Ok(team.set_driver(driver))
+        } else if let Some(constructor) = season.constructor_index(name) {
+            Ok(team.set_constructor(constructor))
+        } else {
+            Err(Error::Argument(format!(
+                "unknown driver or constructor {name:?}"
+            )))
+        }
+    })
+}
+
+fn print_candidates(season: &Season, candidates: &[Candidate], format: Format) -> Result<()> {
+    let names = |c: &Candidate| {
+        (
+            c.team.drivers().map(|d| season.driver_name(d)),
+            c.team.constructors().map(|c| season.constructor_name(c)),
+        )
+    };
+    let entity_names = |entities: &[Entity]| {
+        entities
+            .iter()
+            .map(|&e| entity_name(season, e))
+            .collect::<Vec<_>>()
+    };
+    let with_moves = candidates.iter().any(|c| c.moves.is_some());
+    match format {
+        Format::Table => {
+            print!(
+                "{:>3}  {:<24} {:<24} {:<4} {:>7} {:>6} {:>6}",
+                "#", "Drivers", "Constructors", "DRS", "Points", "Loss", "Cost"
+            );
+            if with_moves {
+                print!(" {:>7} {:>7}  Transfers", "Penalty", "Net");
+            }
+            println!();
+            for (rank, c) in candidates.iter().enumerate() {
+                let (drivers, constructors) = names(c);
+                print!(
+                    "{:>3}  {:<24} {:<24} {:<4} {:>7.2} {:>6.2} {:>6.1}",
+                    rank + 1,
+                    drivers.join(" "),
+                    constructors.join(" "),
+                    season.driver_name(c.evaluation.drs_driver),
+                    c.evaluation.points,
+                    c.evaluation.expected_loss(),
+                    c.cost
+                );
+                if let Some(moves) = &c.moves {
+                    let transfers = entity_names(&moves.out)
+                        .iter()
+                        .zip(entity_names(&moves.into))
+                        .map(|(out, into)| format!("{out} -> {into}"))
+                        .collect::<Vec<_>>();
+                    print!(
+                        " {:>7} {:>7.2}  {}",
+                        moves.penalty,
+                        c.net_points(),
+                        transfers.join(", ")
+                    );
+                }
+                println!();
+            }
+        }
+        Format::Csv => {
+            let csv_error = |err: csv::Error| Error::Parse(format!("writing CSV failed: {err}"));
+            let mut writer = csv::Writer::from_writer(std::io::stdout());
+            let mut header = vec![
+                "Rank",
+                "Drivers",
+                "Constructors",
+                "DRS",
+                "Points",
+                "Hindsight",
+                "Cost",
+            ];
+            if with_moves {
+                header.extend(["Out", "In", "Penalty", "Net"]);
+            }
+            writer.write_record(header).map_err(csv_error)?;
+            for (rank, c) in candidates.iter().enumerate() {
+                let (drivers, constructors) = names(c);
+                let mut record = vec![
+                    (rank + 1).to_string(),
+                    drivers.join(" "),
+                    constructors.join(" "),
+                    season.driver_name(c.evaluation.drs_driver).to_string(),
+                    format!("{:.2}", c.evaluation.points),
+                    format!("{:.2}", c.evaluation.hindsight),
+                    format!("{:.1}", c.cost),
+                ];
+                if let Some(moves) = &c.moves {
+                    record.extend([
+                        entity_names(&moves.out).join(" "),
+                        entity_names(&moves.into).join(" "),
+                        moves.penalty.to_string(),
+                        format!("{:.2}", c.net_points()),
+                    ]);
+                }
+                writer.write_record(record).map_err(csv_error)?;
+            }
+            writer
+                .flush()
+                .map_err(|err| Error::Io("stdout".into(), err))?;
+        }
+        Format::Json => {
+            let teams = candidates
+                .iter()
+                .map(|c| {
+                    let (drivers, constructors) = names(c);
+                    let mut team = serde_json::json!({
+                        "drivers": drivers,
+                        "constructors": constructors,
+                        "drs": season.driver_name(c.evaluation.drs_driver),
+                        "points": rounded(c.evaluation.points),
+                        "hindsight": rounded(c.evaluation.hindsight),
+                        "cost": rounded(c.cost),
+                    });
+                    if let Some(moves) = &c.moves {
+                        team["out"] = entity_names(&moves.out).into();
+                        team["in"] = entity_names(&moves.into).into();
+                        team["penalty"] = moves.penalty.into();
+                        team["net"] = rounded(c.net_points()).into();
+                    }
+                    team
+                })
+                .collect::<Vec<_>>();
+            let json = serde_json::to_string_pretty(&teams)
+                .map_err(|err| Error::Parse(format!("writing JSON failed: {err}")))?;
+            println!("{json}");
+        }
+    }
+    Ok(())
 }
 
-struct SpreadWeeks;
-impl Weights<6> for SpreadWeeks {
-    const WEIGHTS: [f32; 6] = [0.3, 0.3, 0.2, 0.1, 0.1, 0.0];
+/// Rounds to two decimals for output, without the noise of `f32` in `f64`.
+fn rounded(value: f32) -> f64 {
+    (f64::from(value) * 100.0).round() / 100.0
 }
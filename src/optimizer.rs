@@ -0,0 +1,185 @@
+use std::cmp::min;
+
+use clap::ValueEnum;
+
+use crate::{
+    events::Entity,
+    scoring::{self, Absent, Evaluation},
+    season::Season,
+    team::{Team, TeamEnumeration},
+    transfers::{self, TransferPolicy},
+    week::{self, WeekCosts, WeekPoints},
+};
+
+/// How the points of past weeks are weighted to forecast the next race.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Strategy {
+    /// Every week counts the same.
+    Unweighted,
+    /// Only the last week counts.
+    LastWeek,
+    /// The last five weeks count, the recent ones more.
+    SpreadWeeks,
+}
+
+impl Strategy {
+    /// Weights of `weeks` past weeks, the oldest first.
+    pub fn weights(self, weeks: usize) -> Vec<f32> {
+        match self {
+            Strategy::Unweighted => vec![1.0 / weeks as f32; weeks],
+            Strategy::LastWeek => recency_weights::<2, LastWeek>(weeks),
+            Strategy::SpreadWeeks => recency_weights::<6, SpreadWeeks>(weeks),
+        }
+    }
+
+    /// Forecast of a team from the past weeks, weighted by the strategy. The
+    /// drivers of `absent` miss the race and score nothing.
+    pub fn evaluate(self, team: Team, absent: Team, points: &[WeekPoints]) -> Evaluation {
+        scoring::evaluate(&Absent(absent), team, points, &self.weights(points.len()))
+    }
+}
+
+/// Teams to search for: at most `budget` at the latest prices, with all
+/// drivers and constructors of `required` and none of `excluded`. With a
+/// starting team, only teams within `max_transfers` of it are searched.
+pub struct Query {
+    pub budget: f32,
+    pub strategy: Strategy,
+    pub top: usize,
+    pub required: Team,
+    pub excluded: Team,
+    pub start: Option<Start>,
+    pub max_transfers: usize,
+}
+
+/// The team a player has and the free transfers they have left for the next
+/// race.
+pub struct Start {
+    pub team: Team,
+    pub free: usize,
+}
+
+impl Query {
+    fn allows(&self, team: Team) -> bool {
+        team.bitmap() & self.required.bitmap() == self.required.bitmap()
+            && team.bitmap() & self.excluded.bitmap() == 0
+            && self.start.as_ref().is_none_or(|start| {
+                transfers::transfers_between(start.team, team) <= self.max_transfers
+            })
+    }
+}
+
+/// Drivers and constructors sold and bought to get to a team, with the
+/// penalty for the transfers beyond the free ones.
+#[derive(Clone, Debug)]
+pub struct Moves {
+    pub out: Vec<Entity>,
+    pub into: Vec<Entity>,
+    pub penalty: isize,
+}
+
+impl Moves {
+    /// Transfers from `from` to `to`, with the penalty as given.
+    pub fn new(from: Team, to: Team, penalty: isize) -> Self {
+        let entities = |team: Team| {
+            team.drivers()
+                .map(Entity::Driver)
+                .into_iter()
+                .chain(team.constructors().map(Entity::Constructor))
+        };
+        Self {
+            out: entities(from)
+                .filter(|e| !entities(to).any(|t| t == *e))
+                .collect(),
+            into: entities(to)
+                .filter(|e| !entities(from).any(|t| t == *e))
+                .collect(),
+            penalty,
+        }
+    }
+
+    fn between(policy: &TransferPolicy, start: &Start, team: Team) -> Self {
+        let transfers = transfers::transfers_between(start.team, team);
+        Self::new(start.team, team, policy.penalty(transfers, start.free))
+    }
+}
+
+/// A team with its forecast, its cost at the latest prices and how to get
+/// there from the starting team.
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    pub team: Team,
+    pub evaluation: Evaluation,
+    pub cost: f32,
+    pub moves: Option<Moves>,
+}
+
+impl Candidate {
+    /// Forecast points after the transfer penalty.
+    pub fn net_points(&self) -> f32 {
+        self.evaluation.points - self.moves.as_ref().map_or(0, |m| m.penalty) as f32
+    }
+}
+
+/// Returns the `query.top` teams with the best forecast for the race after
+/// the last week with points, after transfer penalties, best first. Drivers
+/// of the starting team who miss the race may be kept and forecast no points.
+pub fn best_teams(
+    season: &Season,
+    points: &[WeekPoints],
+    costs: &[WeekCosts],
+    query: &Query,
+) -> Vec<Candidate> {
+    let Some(prices) = costs.last() else {
+        return Vec::new();
+    };
+    let week = points.len();
+    let (teams, absent) = match &query.start {
+        Some(start) => (
+            TeamEnumeration::keeping(season, week, start.team),
+            start
+                .team
+                .drivers()
+                .into_iter()
+                .filter(|&d| !season.driver_available(d, week))
+                .fold(Team::new(), Team::set_driver),
+        ),
+        None => (TeamEnumeration::new(season, week), Team::new()),
+    };
+    let mut candidates = teams
+        .filter(|&team| query.allows(team) && week::cost_of_team(team, prices) <= query.budget)
+        .map(|team| Candidate {
+            team,
+            evaluation: query.strategy.evaluate(team, absent, points),
+            cost: week::cost_of_team(team, prices),
+            moves: query
+                .start
+                .as_ref()
+                .map(|start| Moves::between(&season.transfers, start, team)),
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| a.net_points().total_cmp(&b.net_points()).reverse());
+    candidates.truncate(query.top);
+    candidates
+}
+
+fn recency_weights<const S: usize, W: Weights<S>>(weeks: usize) -> Vec<f32> {
+    (0..weeks)
+        .map(|w| W::WEIGHTS[min(weeks - w - 1, W::WEIGHTS.len() - 1)])
+        .collect()
+}
+
+/// Weights of the weeks before a race, the last week first.
+trait Weights<const S: usize> {
+    const WEIGHTS: [f32; S];
+}
+
+struct LastWeek;
+impl Weights<2> for LastWeek {
+    const WEIGHTS: [f32; 2] = [1.0, 0.0];
+}
+
+struct SpreadWeeks;
+impl Weights<6> for SpreadWeeks {
+    const WEIGHTS: [f32; 6] = [0.3, 0.3, 0.2, 0.1, 0.1, 0.0];
+}
@@ -0,0 +1,423 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    optimizer::{Moves, Start, Strategy},
+    scoring,
+    season::Season,
+    team::{Team, TeamEnumeration},
+    transfers::{self, TransferPolicy},
+    week::{self, Grid, WeekCosts, WeekPoints},
+};
+
+/// Projected points of every driver and constructor in an upcoming race.
+pub type Projection = Grid<f32>;
+
+/// Projects the races from `first_week` on, `races` of them. Every race gets
+/// the weighted points of the past weeks without their sprint sessions, and
+/// sprint weekends the weighted sprint points of past sprint weekends on top.
+pub fn project(
+    season: &Season,
+    strategy: Strategy,
+    points: &[WeekPoints],
+    first_week: usize,
+    races: usize,
+) -> Vec<Projection> {
+    let all = points.iter().collect::<Vec<_>>();
+    let sprints = points
+        .iter()
+        .zip(&season.races)
+        .filter(|(_, race)| race.sprint)
+        .map(|(week_points, _)| week_points)
+        .collect::<Vec<_>>();
+    let sprint_points =
+        |p: &WeekPoints, d: usize| p.sprint.drivers[d] + p.sprint_qualifying.drivers[d];
+    let drivers = season.drivers.len();
+    let base = Grid {
+        drivers: weighted(strategy, &all, drivers, |p, d| {
+            p.total.drivers[d] - sprint_points(p, d)
+        }),
+        constrs: weighted(strategy, &all, season.constructors.len(), |p, c| {
+            p.total.constrs[c]
+        }),
+    };
+    let sprint = weighted(strategy, &sprints, drivers, sprint_points);
+    (first_week..first_week + races)
+        .map(|week| {
+            let mut projection = base.clone();
+            if season.races.get(week).is_some_and(|race| race.sprint) {
+                for (points, sprint) in projection.drivers.iter_mut().zip(&sprint) {
+                    *points += sprint;
+                }
+            }
+            projection
+        })
+        .collect()
+}
+
+/// Weighted sum of a value of every driver or constructor over `weeks`.
+fn weighted(
+    strategy: Strategy,
+    weeks: &[&WeekPoints],
+    size: usize,
+    value: impl Fn(&WeekPoints, usize) -> isize,
+) -> Vec<f32> {
+    let weights = strategy.weights(weeks.len());
+    (0..size)
+        .map(|i| {
+            weeks
+                .iter()
+                .zip(&weights)
+                .fold(0.0, |sum, (week_points, weight)| {
+                    sum + value(week_points, i) as f32 * weight
+                })
+        })
+        .collect()
+}
+
+/// Projected points of a team, with DRS on its best projected driver.
+pub fn projected_points(team: Team, projection: &Projection) -> f32 {
+    scoring::score_projection(team, projection).total()
+}
+
+/// The team of one race of a plan and how to get there from the race before.
+#[derive(Clone, Debug)]
+pub struct PlanWeek {
+    pub week: usize,
+    pub team: Team,
+    pub free: usize,
+    pub moves: Moves,
+    pub drs_driver: usize,
+    pub points: f32,
+}
+
+/// Teams for a run of upcoming races.
+#[derive(Clone, Debug)]
+pub struct Plan {
+    pub weeks: Vec<PlanWeek>,
+}
+
+impl Plan {
+    /// Projected points of all races after transfer penalties.
+    pub fn total(&self) -> f32 {
+        self.weeks
+            .iter()
+            .map(|w| w.points - w.moves.penalty as f32)
+            .sum()
+    }
+}
+
+/// Limits of a plan: the budget at the latest prices, which are kept for
+/// every race, and the number of teams per race to consider.
+pub struct Limits<'a> {
+    pub prices: &'a WeekCosts,
+    pub budget: f32,
+    pub pool: usize,
+    pub max_transfers: usize,
+}
+
+/// Best step into a state of the search, a team with a number of free
+/// transfers left.
+#[derive(Clone, Copy)]
+struct Step {
+    points: f32,
+    from: Option<(usize, usize)>,
+}
+
+/// Finds the teams for the races from `first_week` on, one per projection,
+/// with the most projected points after transfer penalties. Without a
+/// starting team the first team is picked for free.
+///
+/// Searching every sequence of teams is out of reach, so the search is exact
+/// over a pool of teams: the best `limits.pool` teams of every race, those
+/// within the free transfers of the starting team and the starting team
+/// itself. Chips aren't planned.
+pub fn plan(
+    season: &Season,
+    policy: &TransferPolicy,
+    start: Option<&Start>,
+    first_week: usize,
+    projections: &[Projection],
+    limits: &Limits,
+) -> Option<Plan> {
+    let pool = team_pool(season, start, first_week, projections, limits);
+    let free_states = 1 + start
+        .map_or(0, |s| s.free)
+        .max(policy.free_per_week + policy.max_banked);
+    // Drivers that can't be picked in every race. A team may keep such a
+    // driver, who then scores nothing, but can't bring him in.
+    let unavailable = (first_week..first_week + projections.len())
+        .map(|week| {
+            (0..season.drivers.len())
+                .filter(|&d| !season.driver_available(d, week))
+                .fold(Team::new(), Team::set_driver)
+                .bitmap()
+        })
+        .collect::<Vec<_>>();
+    let allowed = |week: usize, from: Option<usize>, to: usize| {
+        let kept = from.map_or(0, |from| pool[from].bitmap());
+        pool[to].bitmap() & unavailable[week] & !kept == 0
+    };
+    // Projected points of every team of the pool in every race.
+    let projections = projections
+        .iter()
+        .zip(&unavailable)
+        .map(|(projection, &unavailable)| {
+            let mut projection = projection.clone();
+            for (driver, points) in projection.drivers.iter_mut().enumerate() {
+                if unavailable & (1 << driver) != 0 {
+                    *points = 0.0;
+                }
+            }
+            projection
+        })
+        .collect::<Vec<_>>();
+    let points = projections
+        .iter()
+        .map(|projection| {
+            pool.iter()
+                .map(|&team| projected_points(team, projection))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let index = |team: usize, free: usize| team * free_states + free;
+    let mut steps: Vec<Vec<Option<Step>>> = Vec::with_capacity(projections.len());
+    for (week, week_points) in points.iter().enumerate() {
+        let mut next = vec![None; pool.len() * free_states];
+        let mut relax = |to: usize, free: usize, points: f32, from: Option<(usize, usize)>| {
+            let slot: &mut Option<Step> = &mut next[index(to, free)];
+            if slot.is_none_or(|s| points > s.points) {
+                *slot = Some(Step { points, from });
+            }
+        };
+        let previous = match week.checked_sub(1) {
+            Some(w) => states(&steps[w], free_states),
+            None => match start {
+                Some(start) => vec![(0, start.free, 0.0)],
+                None => Vec::new(),
+            },
+        };
+        if week == 0 && start.is_none() {
+            for (to, &points) in week_points.iter().enumerate() {
+                if allowed(week, None, to) {
+                    relax(to, policy.free_per_week, points, None);
+                }
+            }
+        }
+        for (from, free, total) in previous {
+            for (to, &points) in week_points.iter().enumerate() {
+                if !allowed(week, Some(from), to) {
+                    continue;
+                }
+                let transfers = transfers::transfers_between(pool[from], pool[to]);
+                if transfers > limits.max_transfers {
+                    continue;
+                }
+                let penalty = policy.penalty(transfers, free) as f32;
+                let next_free = policy.next_free(free, transfers).min(free_states - 1);
+                let origin = (week > 0 || start.is_some()).then_some((from, free));
+                relax(to, next_free, total + points - penalty, origin);
+            }
+        }
+        steps.push(next);
+    }
+
+    // Walks back from the best final state.
+    let (mut team, mut free, _) = states(steps.last()?, free_states)
+        .into_iter()
+        .max_by(|a, b| a.2.total_cmp(&b.2))?;
+    let mut chosen = Vec::with_capacity(steps.len());
+    for week in (0..steps.len()).rev() {
+        let step = steps[week][index(team, free)]?;
+        chosen.push(team);
+        match step.from {
+            Some((t, f)) => (team, free) = (t, f),
+            None => break,
+        }
+    }
+    chosen.reverse();
+
+    let mut weeks = Vec::with_capacity(chosen.len());
+    let mut previous = start.map(|s| (s.team, s.free));
+    for (week, &to) in chosen.iter().enumerate() {
+        let team = pool[to];
+        let (moves, free) = match previous {
+            Some((from, free)) => {
+                let transfers = transfers::transfers_between(from, team);
+                let penalty = policy.penalty(transfers, free);
+                (Moves::new(from, team, penalty), free)
+            }
+            None => (Moves::new(team, team, 0), policy.free_per_week),
+        };
+        let next_free = if previous.is_some() {
+            policy.next_free(free, moves.out.len())
+        } else {
+            policy.free_per_week
+        };
+        weeks.push(PlanWeek {
+            week: first_week + week,
+            team,
+            free,
+            moves,
+            drs_driver: scoring::score_projection(team, &projections[week]).drs_driver,
+            points: points[week][to],
+        });
+        previous = Some((team, next_free));
+    }
+    Some(Plan { weeks })
+}
+
+/// Reachable states of a race: the team, the free transfers left and the
+/// points so far.
+fn states(steps: &[Option<Step>], free_states: usize) -> Vec<(usize, usize, f32)> {
+    steps
+        .iter()
+        .enumerate()
+        .filter_map(|(i, step)| step.map(|s| (i / free_states, i % free_states, s.points)))
+        .collect()
+}
+
+/// Teams the plan is searched over, the starting team first.
+fn team_pool(
+    season: &Season,
+    start: Option<&Start>,
+    first_week: usize,
+    projections: &[Projection],
+    limits: &Limits,
+) -> Vec<Team> {
+    let mut pool = BTreeSet::new();
+    for (i, projection) in projections.iter().enumerate() {
+        let mut best = TeamEnumeration::new(season, first_week + i)
+            .filter(|&team| week::cost_of_team(team, limits.prices) <= limits.budget)
+            .map(|team| (projected_points(team, projection), team))
+            .collect::<Vec<_>>();
+        best.sort_by(|a, b| a.0.total_cmp(&b.0).reverse());
+        pool.extend(best.iter().take(limits.pool).map(|(_, team)| *team));
+        if let (0, Some(start)) = (i, start) {
+            pool.extend(
+                best.iter()
+                    .filter(|(_, team)| {
+                        transfers::transfers_between(start.team, *team) <= start.free
+                    })
+                    .take(limits.pool)
+                    .map(|(_, team)| *team),
+            );
+        }
+    }
+    let mut teams = Vec::with_capacity(pool.len() + 1);
+    if let Some(start) = start {
+        pool.remove(&start.team);
+        teams.push(start.team);
+    }
+    teams.extend(pool);
+    teams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Entity;
+
+    /// Two races with seven drivers and three constructors. `absent` is a
+    /// driver missing the second race.
+    fn season(absent: usize) -> Season {
+        let drivers = (0..7)
+            .map(|d| {
+                let weeks = if d == absent { "[1]" } else { "[]" };
+                format!("{{ name = \"D{d}\", fantasy_id = {d}, absent_weeks = {weeks} }}")
+            })
+            .collect::<Vec<_>>();
+        let constructors = (0..3)
+            .map(|c| format!("{{ name = \"C{c}\", short = \"C{c}\", fantasy_id = {c} }}"))
+            .collect::<Vec<_>>();
+        toml::from_str(&format!(
+            "year = 2024\n\
+             races = [{{ name = \"R0\" }}, {{ name = \"R1\" }}]\n\
+             drivers = [{}]\n\
+             constructors = [{}]\n",
+            drivers.join(", "),
+            constructors.join(", ")
+        ))
+        .unwrap()
+    }
+
+    fn projection(drivers: [f32; 7]) -> Projection {
+        Grid {
+            drivers: drivers.to_vec(),
+            constrs: vec![5.0, 4.0, 0.0],
+        }
+    }
+
+    fn start_team() -> Team {
+        (0..5)
+            .fold(Team::new(), Team::set_driver)
+            .set_constructor(0)
+            .set_constructor(1)
+    }
+
+    fn plan_two_races(season: &Season, projections: &[Projection], max_transfers: usize) -> Plan {
+        let prices = Grid {
+            drivers: vec![10.0; 7],
+            constrs: vec![10.0; 3],
+        };
+        let limits = Limits {
+            prices: &prices,
+            budget: 70.0,
+            pool: 10,
+            max_transfers,
+        };
+        let start = Start {
+            team: start_team(),
+            free: 2,
+        };
+        plan(
+            season,
+            &season.transfers,
+            Some(&start),
+            0,
+            projections,
+            &limits,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn transfers_in_the_race_it_pays_off() {
+        let projections = [
+            projection([10.0, 9.0, 8.0, 7.0, 6.0, 1.0, 0.0]),
+            projection([10.0, 9.0, 8.0, 7.0, 6.0, 1.0, 100.0]),
+        ];
+        let plan = plan_two_races(&season(usize::MAX), &projections, 2);
+
+        assert_eq!(plan.weeks[0].team, start_team());
+        assert_eq!(plan.weeks[0].points, 59.0);
+        let last = &plan.weeks[1];
+        assert_eq!(last.moves.out, [Entity::Driver(4)]);
+        assert_eq!(last.moves.into, [Entity::Driver(6)]);
+        assert_eq!(last.moves.penalty, 0);
+        assert_eq!(last.drs_driver, 6);
+        assert_eq!(plan.total(), 302.0);
+    }
+
+    #[test]
+    fn keeps_absent_driver_without_points() {
+        let projections = vec![projection([10.0, 9.0, 8.0, 7.0, 6.0, 1.0, 0.0]); 2];
+        let plan = plan_two_races(&season(0), &projections, 0);
+
+        assert_eq!(plan.weeks[1].team, start_team());
+        assert_eq!(plan.weeks[1].drs_driver, 1);
+        assert_eq!(plan.weeks[1].points, 48.0);
+    }
+
+    #[test]
+    fn never_brings_in_absent_driver() {
+        let projections = [
+            projection([10.0, 9.0, 8.0, 7.0, 6.0, 1.0, 0.0]),
+            projection([10.0, 9.0, 8.0, 7.0, 6.0, 1.0, 100.0]),
+        ];
+        let plan = plan_two_races(&season(6), &projections, 2);
+
+        assert!(plan.weeks.iter().all(|w| w.team == start_team()));
+        assert_eq!(plan.total(), 118.0);
+    }
+}
@@ -0,0 +1,331 @@
+use std::path::Path;
+
+use serde_json::Value;
+use toml_edit::DocumentMut;
+
+use crate::{
+    data,
+    error::{Error, Result},
+    season::Season,
+};
+
+/// Feed with every driver and constructor of the fantasy game.
+pub const PLAYER_LIST_PATH: &str = "drivers/1_en.json";
+
+/// An entry of the player list feed.
+#[derive(Clone, Debug)]
+pub struct FeedPlayer {
+    pub id: usize,
+    pub name: String,
+    pub team: String,
+    pub constructor: bool,
+}
+
+/// Result of looking up a roster entry in the player list.
+#[derive(Clone, Debug)]
+pub enum IdMatch {
+    Found(usize),
+    Missing,
+    Ambiguous(Vec<FeedPlayer>),
+}
+
+/// Ids found for every driver and constructor of a season, in roster order.
+#[derive(Clone, Debug)]
+pub struct IdMapping {
+    pub drivers: Vec<IdMatch>,
+    pub constructors: Vec<IdMatch>,
+}
+
+impl IdMapping {
+    /// Number of roster entries without a unique match.
+    pub fn unmatched(&self) -> usize {
+        self.drivers
+            .iter()
+            .chain(&self.constructors)
+            .filter(|m| !matches!(m, IdMatch::Found(_)))
+            .count()
+    }
+}
+
+fn format_error(message: &str) -> Error {
+    Error::Fetch(format!("unexpected player list format: {message}"))
+}
+
+fn text<'a>(player: &'a Value, keys: &[&str]) -> &'a str {
+    keys.iter()
+        .find_map(|key| player.get(key).and_then(Value::as_str))
+        .unwrap_or_default()
+}
+
+/// Parses the player list feed. The list is either the top level array or
+/// found under `Data.Value` or `Value`.
+pub fn parse_player_list(input: &str) -> Result<Vec<FeedPlayer>> {
+    let json: Value = serde_json::from_str(input).map_err(|err| Error::Fetch(err.to_string()))?;
+    let list = [
+        json.get("Data").and_then(|d| d.get("Value")),
+        json.get("Value"),
+        Some(&json),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(Value::as_array)
+    .ok_or_else(|| format_error("no list of players"))?;
+
+    let mut players = Vec::new();
+    for player in list {
+        let id = match player.get("PlayerId") {
+            Some(Value::String(id)) => id.parse().ok(),
+            Some(id) => id.as_u64().map(|id| id as usize),
+            None => None,
+        }
+        .ok_or_else(|| format_error("player without PlayerId"))?;
+        let constructor = text(player, &["PositionName"]).eq_ignore_ascii_case("constructor")
+            || player.get("Skill").and_then(Value::as_u64) == Some(2);
+        players.push(FeedPlayer {
+            id,
+            name: text(player, &["FUllName", "FullName", "DisplayName"]).to_string(),
+            team: text(player, &["TeamName"]).to_string(),
+            constructor,
+        });
+    }
+    Ok(players)
+}
+
+/// Lowercases a name and drops accents, spaces and punctuation, so that
+/// "Pérez" matches "PER" and "Red Bull Racing" contains "RedBull".
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter_map(|c| {
+            let c = match c.to_lowercase().next().unwrap_or(c) {
+                'á' | 'à' | 'â' | 'ä' | 'ã' | 'å' => 'a',
+                'é' | 'è' | 'ê' | 'ë' => 'e',
+                'í' | 'ì' | 'î' | 'ï' => 'i',
+                'ó' | 'ò' | 'ô' | 'ö' | 'õ' | 'ø' => 'o',
+                'ú' | 'ù' | 'û' | 'ü' => 'u',
+                'ñ' => 'n',
+                'ç' => 'c',
+                c => c,
+            };
+            c.is_ascii_alphanumeric().then_some(c)
+        })
+        .collect()
+}
+
+fn unique(candidates: Vec<&FeedPlayer>) -> IdMatch {
+    let mut ids = candidates.iter().map(|p| p.id).collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+    match ids[..] {
+        [] => IdMatch::Missing,
+        [id] => IdMatch::Found(id),
+        _ => IdMatch::Ambiguous(candidates.into_iter().cloned().collect()),
+    }
+}
+
+/// Whether a name of the feed contains our name of a constructor or is part
+/// of it, e.g. "Red Bull Racing" and "RedBull".
+fn same_team(ours: &str, theirs: &str) -> bool {
+    let (ours, theirs) = (normalize(ours), normalize(theirs));
+    !theirs.is_empty() && (theirs.contains(&ours) || ours.contains(&theirs))
+}
+
+/// Finds a driver by their three letter code, which is the start of their
+/// last name, or of any other part of the name for names like Zhou Guanyu.
+/// Drivers of the same name are told apart by their `constructor`.
+fn match_driver(code: &str, constructor: Option<&str>, players: &[FeedPlayer]) -> IdMatch {
+    let code = normalize(code);
+    let drivers = players.iter().filter(|p| !p.constructor);
+    let words = |p: &FeedPlayer| p.name.split_whitespace().map(normalize).collect::<Vec<_>>();
+    let mut candidates = drivers
+        .clone()
+        .filter(|p| words(p).last().is_some_and(|w| w.starts_with(&code)))
+        .collect::<Vec<_>>();
+    if candidates.is_empty() {
+        candidates = drivers
+            .filter(|p| words(p).iter().any(|w| w.starts_with(&code)))
+            .collect();
+    }
+    if let (IdMatch::Ambiguous(_), Some(constructor)) = (unique(candidates.clone()), constructor) {
+        let in_team = candidates
+            .iter()
+            .copied()
+            .filter(|p| same_team(constructor, &p.team))
+            .collect::<Vec<_>>();
+        if !in_team.is_empty() {
+            candidates = in_team;
+        }
+    }
+    unique(candidates)
+}
+
+/// Finds a constructor whose name or team name contains ours or is part of
+/// ours.
+fn match_constructor(name: &str, players: &[FeedPlayer]) -> IdMatch {
+    unique(
+        players
+            .iter()
+            .filter(|p| p.constructor)
+            .filter(|p| same_team(name, &p.name) || same_team(name, &p.team))
+            .collect(),
+    )
+}
+
+/// Looks up every driver and constructor of the season in the player list.
+pub fn match_ids(season: &Season, players: &[FeedPlayer]) -> IdMapping {
+    IdMapping {
+        drivers: season
+            .drivers
+            .iter()
+            .map(|d| match_driver(&d.name, d.constructor.as_deref(), players))
+            .collect(),
+        constructors: season
+            .constructors
+            .iter()
+            .map(|c| match_constructor(&c.name, players))
+            .collect(),
+    }
+}
+
+/// Writes the ids that were found into the season file, keeping everything
+/// else in the file as it is. Entries without a unique match keep their id.
+pub fn write_ids(file: &Path, mapping: &IdMapping) -> Result<()> {
+    let io_error = |err| Error::Io(file.to_path_buf(), err);
+    let content = std::fs::read_to_string(file).map_err(io_error)?;
+    let mut document: DocumentMut = content
+        .parse()
+        .map_err(|err| Error::Parse(format!("{}: {err}", file.display())))?;
+    for (key, matches) in [
+        ("drivers", &mapping.drivers),
+        ("constructors", &mapping.constructors),
+    ] {
+        let Some(entries) = document
+            .get_mut(key)
+            .and_then(|item| item.as_array_of_tables_mut())
+        else {
+            continue;
+        };
+        for (entry, id_match) in entries.iter_mut().zip(matches) {
+            if let IdMatch::Found(id) = id_match {
+                entry["fantasy_id"] = toml_edit::value(*id as i64);
+            }
+        }
+    }
+    data::replace_files(&[(file.to_path_buf(), document.to_string())])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::season::test_season;
+
+    const PLAYER_LIST: &str = include_str!("../tests/fixtures/player_list.json");
+
+    fn found(season: &Season, mapping: &IdMapping, driver: &str) -> Option<usize> {
+        match mapping.drivers[season.driver_index(driver).unwrap()] {
+            IdMatch::Found(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn parses_player_list() {
+        let players = parse_player_list(PLAYER_LIST).unwrap();
+        assert_eq!(players.len(), 32);
+        assert_eq!(players.iter().filter(|p| p.constructor).count(), 10);
+        let hulkenberg = players.iter().find(|p| p.id == 111).unwrap();
+        assert_eq!(hulkenberg.name, "Nico Hülkenberg");
+        assert_eq!(hulkenberg.team, "Haas F1 Team");
+        assert!(!hulkenberg.constructor);
+    }
+
+    #[test]
+    fn reads_list_at_top_level_and_numeric_ids() {
+        let players =
+            parse_player_list(r#"[{"PlayerId": 7, "FullName": "Alpine", "Skill": 2}]"#).unwrap();
+        assert_eq!(players[0].id, 7);
+        assert!(players[0].constructor);
+        assert!(parse_player_list(r#"{"Data": {}}"#).is_err());
+    }
+
+    #[test]
+    fn matches_roster_against_player_list() {
+        let season = test_season();
+        let mapping = match_ids(&season, &parse_player_list(PLAYER_LIST).unwrap());
+
+        assert_eq!(found(&season, &mapping, "VER"), Some(131));
+        assert_eq!(found(&season, &mapping, "PIA"), Some(1982));
+        // Accents are ignored.
+        assert_eq!(found(&season, &mapping, "HUL"), Some(111));
+        // The family name comes first.
+        assert_eq!(found(&season, &mapping, "ZHO"), Some(134));
+        for (constructor, id_match) in season.constructors.iter().zip(&mapping.constructors) {
+            assert!(
+                matches!(id_match, IdMatch::Found(id) if *id == constructor.fantasy_id),
+                "{}: {id_match:?}",
+                constructor.name
+            );
+        }
+    }
+
+    #[test]
+    fn tells_drivers_of_the_same_name_apart_by_team() {
+        let season = test_season();
+        let mapping = match_ids(&season, &parse_player_list(PLAYER_LIST).unwrap());
+
+        // The list has a stale second entry for Pérez with his old team.
+        assert_eq!(found(&season, &mapping, "PER"), Some(121));
+        assert_eq!(mapping.unmatched(), 0);
+    }
+
+    #[test]
+    fn flags_ambiguous_entries() {
+        let season = test_season();
+        let mut players = parse_player_list(PLAYER_LIST).unwrap();
+        for player in players.iter_mut().filter(|p| p.id == 1121) {
+            player.team = "Red Bull Racing".to_string();
+        }
+        let mapping = match_ids(&season, &players);
+
+        let IdMatch::Ambiguous(candidates) = &mapping.drivers[season.driver_index("PER").unwrap()]
+        else {
+            panic!("PER should be ambiguous");
+        };
+        let mut ids = candidates.iter().map(|p| p.id).collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, [121, 1121]);
+        assert_eq!(mapping.unmatched(), 1);
+    }
+
+    #[test]
+    fn flags_missing_entries() {
+        let season = test_season();
+        let mapping = match_ids(&season, &[]);
+        assert!(matches!(mapping.drivers[0], IdMatch::Missing));
+        assert_eq!(
+            mapping.unmatched(),
+            season.drivers.len() + season.constructors.len()
+        );
+    }
+
+    #[test]
+    fn writes_found_ids_only() {
+        let dir = std::env::temp_dir().join(format!("f1-fantasy-ids-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("season.toml");
+        let original = std::fs::read_to_string(test_season().data_file("season.toml")).unwrap();
+        std::fs::write(
+            &file,
+            original.replace("fantasy_id = 131", "fantasy_id = 1"),
+        )
+        .unwrap();
+
+        let season = Season::load(&file).unwrap();
+        let mapping = match_ids(&season, &parse_player_list(PLAYER_LIST).unwrap());
+        write_ids(&file, &mapping).unwrap();
+        let written = std::fs::read_to_string(&file).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // VER gets its id back and PER is found by his team.
+        assert_eq!(written, original);
+    }
+}
@@ -0,0 +1,87 @@
+use serde::Deserialize;
+
+use crate::week::{Grid, WeekCosts, WeekPoints};
+
+/// Model of how prices move after a race: by the average points per million
+/// over the last `races` races, with separate steps for expensive and cheap
+/// players. The bands are set in the season file, `prices` reports how well
+/// they match the actual changes.
+#[derive(Deserialize, Clone, Debug)]
+pub struct PriceRules {
+    pub races: usize,
+    /// Price bands from the most expensive down. A player belongs to the first
+    /// band whose `min_price` they reach.
+    pub bands: Vec<PriceBand>,
+}
+
+/// Changes of a price band: `changes[i]` applies if the points per million
+/// exceed `thresholds[i]`, the last change if they exceed none of them.
+#[derive(Deserialize, Clone, Debug)]
+pub struct PriceBand {
+    pub min_price: f32,
+    pub thresholds: Vec<f32>,
+    pub changes: Vec<f32>,
+}
+
+/// Expected price move of a player after a race.
+#[derive(Clone, Copy, Debug)]
+pub struct PricePrediction {
+    pub price: f32,
+    pub points_per_million: f32,
+    pub change: f32,
+}
+
+impl PriceRules {
+    fn change(&self, price: f32, points_per_million: f32) -> f32 {
+        let Some(band) = self.bands.iter().find(|b| price >= b.min_price) else {
+            return 0.0;
+        };
+        let step = band
+            .thresholds
+            .iter()
+            .position(|&t| points_per_million > t)
+            .unwrap_or(band.thresholds.len());
+        band.changes.get(step).copied().unwrap_or_default()
+    }
+
+    /// Predicts the price moves after `week` from the points and prices of
+    /// the races up to it. `costs[week]` are the prices going into the race.
+    /// Players without a price, e.g. drivers not part of the grid, get none.
+    pub fn predict(
+        &self,
+        points: &[WeekPoints],
+        costs: &[WeekCosts],
+        week: usize,
+    ) -> Grid<Option<PricePrediction>> {
+        let first = (week + 1).saturating_sub(self.races);
+        let predict = |price: fn(&WeekCosts) -> &[f32], total: fn(&WeekPoints) -> &[isize]| {
+            (0..price(&costs[week]).len())
+                .map(|i| {
+                    let races = (first..=week)
+                        .filter(|&w| price(&costs[w])[i] > 0.0)
+                        .map(|w| total(&points[w])[i] as f32 / price(&costs[w])[i])
+                        .collect::<Vec<_>>();
+                    let current = price(&costs[week])[i];
+                    if current <= 0.0 || races.is_empty() {
+                        return None;
+                    }
+                    let points_per_million = races.iter().sum::<f32>() / races.len() as f32;
+                    Some(PricePrediction {
+                        price: current,
+                        points_per_million,
+                        change: self.change(current, points_per_million),
+                    })
+                })
+                .collect()
+        };
+        Grid {
+            drivers: predict(|c| &c.drivers, |p| &p.total.drivers),
+            constrs: predict(|c| &c.constrs, |p| &p.total.constrs),
+        }
+    }
+}
+
+/// Rounds a price change to the game's steps of 100k.
+pub fn round_change(change: f32) -> f32 {
+    (change * 10.0).round() / 10.0
+}
@@ -3,21 +3,28 @@ use std::fs::File;
 use xmltree::{Element, XMLNode};
 
 use crate::{
+    error::{Error, Result},
     league::League,
-    team::{self, Chip, ExtendedTeam},
-    week::{self, WeekCosts, WeekPoints},
+    scoring,
+    season::Season,
+    team::{Chip, ExtendedTeam},
+    week::{WeekCosts, WeekPoints},
 };
 
-pub fn table_template() -> Element {
-    let content =
-        std::fs::read_to_string("resources/table_template.svg").expect("can't read table template");
-    Element::parse(content.as_bytes()).expect("invalid template")
+pub const TABLE_TEMPLATE_FILE: &str = "resources/table_template.svg";
+pub const POINTS_TEMPLATE_FILE: &str = "resources/points_template.svg";
+
+pub fn table_template() -> Result<Element> {
+    load_template(TABLE_TEMPLATE_FILE)
+}
+
+pub fn points_template() -> Result<Element> {
+    load_template(POINTS_TEMPLATE_FILE)
 }
 
-pub fn points_template() -> Element {
-    let content = std::fs::read_to_string("resources/points_template.svg")
-        .expect("can't read points template");
-    Element::parse(content.as_bytes()).expect("invalid template")
+fn load_template(file: &str) -> Result<Element> {
+    let content = std::fs::read_to_string(file).map_err(|err| Error::Io(file.into(), err))?;
+    Element::parse(content.as_bytes()).map_err(|err| Error::Template(format!("{file}: {err}")))
 }
 
 pub fn find_label_recursive<'a>(tree: &'a mut Element, name: &str) -> Option<&'a mut Element> {
@@ -39,30 +46,36 @@ pub fn find_label_recursive<'a>(tree: &'a mut Element, name: &str) -> Option<&'a
     None
 }
 
-fn pick_scale<const SIZE: usize>(d2f: &[[usize; SIZE]]) -> [usize; 4] {
-    let max = *d2f.iter().map(|l| l.iter().max().unwrap()).max().unwrap();
+fn find_label<'a>(tree: &'a mut Element, name: &str) -> Result<&'a mut Element> {
+    find_label_recursive(tree, name).ok_or_else(|| Error::MissingLabel(name.to_string()))
+}
+
+fn write_document(document: &Element, output: File) -> Result<()> {
+    document
+        .write(output)
+        .map_err(|err| Error::Template(err.to_string()))
+}
+
+fn pick_scale(d2f: &[Vec<usize>]) -> [usize; 4] {
+    let max = d2f.iter().flatten().copied().max().unwrap_or_default();
     let mut lb = 50;
-    while 4 * lb < max - 30 {
+    while 4 * lb < max.saturating_sub(30) {
         lb += 50;
     }
     [lb, 2 * lb, 3 * lb, 4 * lb]
 }
 
-fn d2f_map<const SIZE: usize>(
-    d2f: &[[usize; SIZE]],
-    offset: f32,
-    height_point: f32,
-) -> Vec<[f32; SIZE]> {
+fn d2f_map(d2f: &[Vec<usize>], offset: f32, height_point: f32) -> Vec<Vec<f32>> {
     d2f.iter()
-        .map(|l| l.map(|points| offset - (points as f32 * height_point)))
+        .map(|l| {
+            l.iter()
+                .map(|&points| offset - (points as f32 * height_point))
+                .collect()
+        })
         .collect()
 }
 
-pub fn render_chart<const SIZE: usize>(
-    league: &League<SIZE>,
-    week_points: &[WeekPoints],
-    output: File,
-) {
+pub fn render_chart(league: &League, week_points: &[WeekPoints], output: File) -> Result<()> {
     let x_offset = 62_f32;
     let y_offset = 560_f32;
     let height_first_bar = 100_f32;
@@ -72,59 +85,63 @@ pub fn render_chart<const SIZE: usize>(
     let height_point = height_first_bar / scale[0] as f32;
     let points_relative = d2f_map(&points, y_offset, height_point);
 
-    let mut document = points_template();
+    let mut document = points_template()?;
     for (index, value) in scale.into_iter().enumerate() {
-        let bar = find_label_recursive(&mut document, &format!("bar{}", index + 1)).unwrap();
-        let text_field = find_label_recursive(bar, "number").unwrap();
+        let bar = find_label(&mut document, &format!("bar{}", index + 1))?;
+        let text_field = find_label(bar, "number")?;
         set_text(text_field, format!("{:0>3}", value));
     }
 
     for (index, team) in league.names.iter().enumerate() {
-        let legend =
-            find_label_recursive(&mut document, &format!("legend_team{}", index + 1)).unwrap();
-        let name_field = find_label_recursive(legend, "name").unwrap();
+        let legend = find_label(&mut document, &format!("legend_team{}", index + 1))?;
+        let name_field = find_label(legend, "name")?;
         set_text(name_field, team.clone());
     }
 
     for index in 0..league.names.len() {
-        let coords = (0..week_points.len())
-            .map(|week| {
+        let coords = points_relative
+            .iter()
+            .enumerate()
+            .map(|(week, points)| {
                 let x = x_offset + (week as f32 * week_offset);
-                let y = points_relative[week][index];
+                let y = points[index];
                 format!("{},{}", x, y)
             })
             .collect::<Vec<_>>()
             .join(" ");
         let coordinates = format!("M {coords}");
-        let player_line =
-            find_label_recursive(&mut document, &format!("line_team{}", index + 1)).unwrap();
+        let player_line = find_label(&mut document, &format!("line_team{}", index + 1))?;
         player_line
             .attributes
             .insert(String::from("d"), coordinates);
     }
-    document.write(output).unwrap();
+    for label in ["legend_team", "line_team"] {
+        hide_unused(&mut document, label, league.size());
+    }
+    write_document(&document, output)
 }
 
-pub fn render_league_overview<const SIZE: usize>(
-    league: &League<SIZE>,
+pub fn render_league_overview(
+    season: &Season,
+    league: &League,
     week_points: &[WeekPoints],
     week_costs: &[WeekCosts],
+    week: usize,
     output: File,
-) {
-    let mut tree = table_template();
-    let ps = league.points_for_all(&week_points);
-    let mut team_points = Vec::from_iter(ps.last().unwrap().iter().enumerate());
+) -> Result<()> {
+    let mut tree = table_template()?;
+    let ps = league.points_for_all(week_points);
+    let mut team_points = Vec::from_iter(ps[week].iter().enumerate());
     team_points.sort_by(|a, b| a.1.cmp(b.1).reverse());
     for (index, (team, points)) in team_points.iter().enumerate() {
-        let week = ps.len() - 1;
-        let points_rel = league.calculate_points_week(week, *team, &week_points);
-        let budget = league.calculate_budget(week, *team, &week_costs);
-        let budget_rel = budget - league.calculate_budget(week - 1, *team, &week_costs);
+        let points_rel = league.calculate_points_week(week, *team, week_points);
+        let ledger = league.ledger(*team, week_costs)?;
+        let budget = ledger.budget(Some(week));
+        let budget_rel = budget - ledger.budget(week.checked_sub(1));
         let entry_name = format!("entry{}", index + 1);
-        let optimal_points =
-            league.optimal_result(*team, league.teams.len() - 1, week_points, week_costs);
-        let optimal_points_rel = points_rel + league.teams[week][*team].negative - optimal_points;
-        let entry = find_label_recursive(&mut tree, &entry_name).unwrap();
+        let optimal_points = league.optimal_result(season, *team, week, week_points, week_costs)?;
+        let optimal_points_rel = points_rel - optimal_points;
+        let entry = find_label(&mut tree, &entry_name)?;
         set_general_player_data(
             entry,
             league.names[*team].clone(),
@@ -134,12 +151,19 @@ pub fn render_league_overview<const SIZE: usize>(
             budget_rel,
             optimal_points,
             optimal_points_rel,
-        );
-        set_player_team(entry, &league.teams[week][*team], &week_points[week])
+        )?;
+        set_player_team(
+            season,
+            entry,
+            &league.teams[week][*team],
+            &week_points[week],
+        )?;
     }
-    tree.write(output).unwrap();
+    hide_unused(&mut tree, "entry", league.size());
+    write_document(&tree, output)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn set_general_player_data(
     tree: &mut Element,
     name: String,
@@ -149,38 +173,44 @@ fn set_general_player_data(
     budget_rel: f32,
     optimal_points: isize,
     optimal_points_rel: isize,
-) {
+) -> Result<()> {
     let budget_left = (budget + 0.001).floor();
     let budget_right = ((budget + 0.001) - budget_left) * 100.0;
 
-    let elm_name = find_label_recursive(tree, "team_name").unwrap();
+    let elm_name = find_label(tree, "team_name")?;
     elm_name.children[0] = XMLNode::Text(name);
-    let elm_points = find_label_recursive(tree, "all_points").unwrap();
+    let elm_points = find_label(tree, "all_points")?;
     elm_points.children[0] = XMLNode::Text(format!("{:0>4}", points));
-    let elm_points_rel = find_label_recursive(tree, "points_rel").unwrap();
+    let elm_points_rel = find_label(tree, "points_rel")?;
     if points_rel >= 0 {
         elm_points_rel.children[0] = XMLNode::Text(format!("(+{: >3})", points_rel));
     } else {
         elm_points_rel.children[0] = XMLNode::Text(format!("(-{: >3})", points_rel.abs()));
     }
-    let elm_budget = find_label_recursive(tree, "budget").unwrap();
+    let elm_budget = find_label(tree, "budget")?;
     elm_budget.children[0] = XMLNode::Text(format!("{:0>3}", (budget_left as isize)));
-    let elm_budget_dec = find_label_recursive(tree, "budget_dec").unwrap();
+    let elm_budget_dec = find_label(tree, "budget_dec")?;
     elm_budget_dec.children[0] = XMLNode::Text(format!(".{:>2}", (budget_right as isize)));
-    let elm_budget_rel = find_label_recursive(tree, "budget_rel").unwrap();
+    let elm_budget_rel = find_label(tree, "budget_rel")?;
     elm_budget_rel.children[0] = XMLNode::Text(format!("({:>+.2})", budget_rel));
-    let elm_opt_points = find_label_recursive(tree, "optimal_result").unwrap();
+    let elm_opt_points = find_label(tree, "optimal_result")?;
     set_text(elm_opt_points, optimal_points.to_string());
-    let elm_opt_points_rel = find_label_recursive(tree, "optimal_result_rel").unwrap();
+    let elm_opt_points_rel = find_label(tree, "optimal_result_rel")?;
     set_text(elm_opt_points_rel, format!("({})", optimal_points_rel));
+    Ok(())
 }
 
-fn set_player_team(tree: &mut Element, team: &ExtendedTeam, week_points: &WeekPoints) {
-    let chip_badge = find_label_recursive(tree, "chip").unwrap();
-    let (driver_map, constructor_map) = week::point_maps(team, week_points);
+fn set_player_team(
+    season: &Season,
+    tree: &mut Element,
+    team: &ExtendedTeam,
+    week_points: &WeekPoints,
+) -> Result<()> {
+    let chip_badge = find_label(tree, "chip")?;
+    let score = scoring::score_ext_team(team, week_points);
     let mut ff_driver = None;
     if let Some(chip) = &team.chip {
-        let text_field = find_label_recursive(chip_badge, "name").unwrap();
+        let text_field = find_label(chip_badge, "name")?;
         set_text(text_field, chip.short_name());
         if let Chip::FinalFix(ff_d, _) = chip {
             ff_driver = Some(*ff_d);
@@ -189,56 +219,65 @@ fn set_player_team(tree: &mut Element, team: &ExtendedTeam, week_points: &WeekPo
         disable(chip_badge);
     }
 
-    let mut driver_points = team.team.drivers().map(|d| (driver_map[&d], d));
+    let mut driver_points = score.drivers.map(|(d, p)| (p, d));
     driver_points.sort_by(|(p1, _), (p2, _)| p1.cmp(p2).reverse());
     for (i, (p, driver)) in driver_points.iter().enumerate() {
-        let driver_panel = find_label_recursive(tree, &format!("driver{}", i + 1)).unwrap();
-        let driver_name_field = find_label_recursive(driver_panel, "name").unwrap();
-        set_text(driver_name_field, team::DRIVERS[*driver].into());
-        let driver_points_field = find_label_recursive(driver_panel, "points").unwrap();
+        let driver_panel = find_label(tree, &format!("driver{}", i + 1))?;
+        let driver_name_field = find_label(driver_panel, "name")?;
+        set_text(driver_name_field, season.driver_name(*driver).into());
+        let driver_points_field = find_label(driver_panel, "points")?;
         set_text(driver_points_field, p.to_string());
-        if (team.chip != Some(Chip::AutoPilot) && team.drs_driver != *driver)
-            || (team.chip == Some(Chip::AutoPilot) && i != 0)
-        {
-            let drs_badge = find_label_recursive(driver_panel, "badge_drs_driver").unwrap();
+        if score.drs_driver != *driver {
+            let drs_badge = find_label(driver_panel, "badge_drs_driver")?;
             disable(drs_badge);
         }
-        if team.chip == None || team.chip.as_ref().unwrap() != &Chip::ExtraDRS(*driver) {
-            let extra_drs_badge = find_label_recursive(driver_panel, "badge_extra_drs").unwrap();
+        if team.chip != Some(Chip::ExtraDRS(*driver)) {
+            let extra_drs_badge = find_label(driver_panel, "badge_extra_drs")?;
             disable(extra_drs_badge);
         }
         if ff_driver.is_none() || ff_driver.unwrap() != *driver {
-            let ff_badge = find_label_recursive(driver_panel, "badge_final_fix").unwrap();
+            let ff_badge = find_label(driver_panel, "badge_final_fix")?;
             disable(ff_badge);
         }
     }
 
-    let ff_panel = find_label_recursive(tree, "driver6".into()).unwrap();
-    if let Some(Chip::FinalFix(_, ff_sub)) = team.chip {
-        let driver_name_field = find_label_recursive(ff_panel, "name").unwrap();
-        set_text(driver_name_field, team::DRIVERS[ff_sub].into());
-        let driver_points_field = find_label_recursive(ff_panel, "points").unwrap();
-        set_text(driver_points_field, driver_map[&ff_sub].to_string());
+    let ff_panel = find_label(tree, "driver6")?;
+    if let Some((ff_sub, p)) = score.substitute {
+        let driver_name_field = find_label(ff_panel, "name")?;
+        set_text(driver_name_field, season.driver_name(ff_sub).into());
+        let driver_points_field = find_label(ff_panel, "points")?;
+        set_text(driver_points_field, p.to_string());
     } else {
         disable(ff_panel);
     }
 
-    let mut constr_points = team.team.constructors().map(|c| (constructor_map[&c], c));
+    let mut constr_points = score.constructors.map(|(c, p)| (p, c));
     constr_points.sort_by(|(p1, _), (p2, _)| p1.cmp(p2).reverse());
     for (i, (p, constr)) in constr_points.iter().enumerate() {
-        let constr_panel = find_label_recursive(tree, &format!("constructor{}", i + 1)).unwrap();
-        let constr_name_field = find_label_recursive(constr_panel, "name").unwrap();
-        set_text(constr_name_field, team::CONSTRUCTORS_SHORT[*constr].into());
-        let constr_points_field = find_label_recursive(constr_panel, "points").unwrap();
+        let constr_panel = find_label(tree, &format!("constructor{}", i + 1))?;
+        let constr_name_field = find_label(constr_panel, "name")?;
+        set_text(constr_name_field, season.constructor_short(*constr).into());
+        let constr_points_field = find_label(constr_panel, "points")?;
         set_text(constr_points_field, p.to_string());
     }
-    return;
+    Ok(())
 }
 
 fn set_text(text_field: &mut Element, text: String) {
     text_field.children[0] = XMLNode::Text(text);
 }
 
+/// Hides the numbered elements `{label}{n}` of a template that are left over
+/// after the first `used` ones were filled.
+fn hide_unused(tree: &mut Element, label: &str, used: usize) {
+    for n in used + 1.. {
+        match find_label_recursive(tree, &format!("{label}{n}")) {
+            Some(element) => disable(element),
+            None => break,
+        }
+    }
+}
+
 fn disable(element: &mut Element) {
     element
         .attributes
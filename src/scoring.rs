@@ -0,0 +1,395 @@
+use std::{iter::Sum, ops::AddAssign};
+
+use crate::{
+    team::{Chip, ExtendedTeam, Team},
+    week::{Grid, WeekPoints},
+};
+
+/// How a driver boosted by DRS (or a chip) is chosen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pick {
+    /// The driver picked before the race.
+    Driver(usize),
+    /// The best scoring driver of the team, known only after the race.
+    Hindsight,
+}
+
+/// Points of every driver and constructor of a team in a week, with all
+/// boosts and chips applied. Forecasts score in fractional points.
+#[derive(Clone, Copy, Debug)]
+pub struct TeamScore<T = isize> {
+    pub drivers: [(usize, T); 5],
+    pub constructors: [(usize, T); 2],
+    /// Driver brought in by the Final Fix, with the points scored after it.
+    pub substitute: Option<(usize, T)>,
+    /// Driver whose points were doubled by DRS.
+    pub drs_driver: usize,
+}
+
+impl<T: Copy + PartialOrd + AddAssign + Sum> TeamScore<T> {
+    /// Score of a team before any boost.
+    fn new(
+        team: Team,
+        driver_points: impl Fn(usize) -> T,
+        constr_points: impl Fn(usize) -> T,
+    ) -> Self {
+        Self {
+            drivers: team.drivers().map(|d| (d, driver_points(d))),
+            constructors: team.constructors().map(|c| (c, constr_points(c))),
+            substitute: None,
+            drs_driver: 0,
+        }
+    }
+
+    pub fn total(&self) -> T {
+        self.drivers
+            .iter()
+            .chain(&self.constructors)
+            .chain(&self.substitute)
+            .map(|(_, p)| *p)
+            .sum()
+    }
+
+    /// Points of a driver of the team or its substitute.
+    pub fn driver(&self, driver: usize) -> Option<T> {
+        self.drivers
+            .iter()
+            .chain(&self.substitute)
+            .find(|(d, _)| *d == driver)
+            .map(|(_, p)| *p)
+    }
+
+    fn driver_mut(&mut self, driver: usize) -> Option<&mut T> {
+        self.drivers
+            .iter_mut()
+            .find(|(d, _)| *d == driver)
+            .map(|(_, p)| p)
+    }
+
+    /// Best scoring driver of the team, ignoring `except`. The first driver
+    /// wins ties.
+    fn best_driver(&self, except: Option<usize>) -> usize {
+        let mut best: Option<(usize, T)> = None;
+        for &(driver, points) in &self.drivers {
+            if Some(driver) != except && best.is_none_or(|(_, p)| points > p) {
+                best = Some((driver, points));
+            }
+        }
+        best.map_or(self.drivers[0].0, |(driver, _)| driver)
+    }
+
+    /// Doubles the points of the DRS driver.
+    fn apply_drs(&mut self, pick: Pick) {
+        self.drs_driver = match pick {
+            Pick::Driver(driver) => driver,
+            Pick::Hindsight => self.best_driver(None),
+        };
+        if let Some(points) = self.driver_mut(self.drs_driver) {
+            let boost = *points;
+            *points += boost;
+        }
+    }
+}
+
+/// Effect of a chip on the points of a team. Every step has the scoring of a
+/// week without a chip as default.
+pub trait ChipRule {
+    /// Points of a driver before boosts.
+    fn driver_points(&self, week_points: &WeekPoints, driver: usize) -> isize {
+        week_points.total.drivers[driver]
+    }
+
+    fn constructor_points(&self, week_points: &WeekPoints, constructor: usize) -> isize {
+        week_points.total.constrs[constructor]
+    }
+
+    /// How the driver whose points are doubled is chosen.
+    fn drs_pick(&self, pick: Pick) -> Pick {
+        pick
+    }
+
+    /// Changes to the score once the DRS boost has been applied.
+    fn adjust(&self, _week_points: &WeekPoints, _score: &mut TeamScore) {}
+}
+
+/// Limitless and Wildcard only change which teams can be picked, not how
+/// they score.
+pub struct NoChip;
+
+impl ChipRule for NoChip {}
+
+/// Drivers of the team who miss the race score nothing, like a driver kept
+/// through an absence.
+pub struct Absent(pub Team);
+
+impl ChipRule for Absent {
+    fn driver_points(&self, week_points: &WeekPoints, driver: usize) -> isize {
+        if self.0.bitmap() & (1 << driver) != 0 {
+            0
+        } else {
+            week_points.total.drivers[driver]
+        }
+    }
+}
+
+/// Negative points of drivers and constructors are ignored.
+pub struct NoNegative;
+
+impl ChipRule for NoNegative {
+    fn driver_points(&self, week_points: &WeekPoints, driver: usize) -> isize {
+        week_points.total.drivers[driver] + week_points.negative.drivers[driver]
+    }
+
+    fn constructor_points(&self, week_points: &WeekPoints, constructor: usize) -> isize {
+        week_points.total.constrs[constructor] + week_points.negative.constrs[constructor]
+    }
+}
+
+/// DRS goes to the best scoring driver after the race.
+pub struct AutoPilot;
+
+impl ChipRule for AutoPilot {
+    fn drs_pick(&self, _pick: Pick) -> Pick {
+        Pick::Hindsight
+    }
+}
+
+/// A second driver scores triple points.
+pub struct ExtraDrs(pub Pick);
+
+impl ChipRule for ExtraDrs {
+    fn adjust(&self, week_points: &WeekPoints, score: &mut TeamScore) {
+        let driver = match self.0 {
+            Pick::Driver(driver) => driver,
+            Pick::Hindsight => score.best_driver(Some(score.drs_driver)),
+        };
+        if let Some(points) = score.driver_mut(driver) {
+            *points += 2 * week_points.total.drivers[driver];
+        }
+    }
+}
+
+/// A driver is replaced after qualifying, which on sprint weekends is after
+/// the sprint as well, so the substitute only scores the race. A DRS boost
+/// moves with the replacement.
+pub struct FinalFix {
+    pub out: usize,
+    pub sub: usize,
+}
+
+impl ChipRule for FinalFix {
+    fn adjust(&self, week_points: &WeekPoints, score: &mut TeamScore) {
+        if score.driver(self.sub).is_some() {
+            return;
+        }
+        let factor = if self.out == score.drs_driver { 2 } else { 1 };
+        if let Some(points) = score.driver_mut(self.out) {
+            *points -= factor * week_points.race.drivers[self.out];
+            score.substitute = Some((self.sub, factor * week_points.race.drivers[self.sub]));
+        }
+    }
+}
+
+/// Rule of a chip as played, with every driver picked before the race.
+pub fn chip_rule(chip: Option<&Chip>) -> Box<dyn ChipRule> {
+    rule(chip, Pick::Driver)
+}
+
+/// Rule of a chip with the drivers it boosts picked in hindsight, for the
+/// best result possible with the chip.
+pub fn hindsight_rule(chip: Option<&Chip>) -> Box<dyn ChipRule> {
+    rule(chip, |_| Pick::Hindsight)
+}
+
+fn rule(chip: Option<&Chip>, pick: impl Fn(usize) -> Pick) -> Box<dyn ChipRule> {
+    match chip {
+        None | Some(Chip::Limitless) | Some(Chip::Wildcard) => Box::new(NoChip),
+        Some(Chip::NoNegative) => Box::new(NoNegative),
+        Some(Chip::AutoPilot) => Box::new(AutoPilot),
+        Some(Chip::ExtraDRS(driver)) => Box::new(ExtraDrs(pick(*driver))),
+        Some(&Chip::FinalFix(out, sub)) => Box::new(FinalFix { out, sub }),
+    }
+}
+
+/// Scores a team under a chip's rule.
+pub fn score_team(
+    rule: &dyn ChipRule,
+    team: Team,
+    drs: Pick,
+    week_points: &WeekPoints,
+) -> TeamScore {
+    let mut score = TeamScore::new(
+        team,
+        |d| rule.driver_points(week_points, d),
+        |c| rule.constructor_points(week_points, c),
+    );
+    score.apply_drs(rule.drs_pick(drs));
+    rule.adjust(week_points, &mut score);
+    score
+}
+
+/// Scores a team on projected points without a chip, with DRS on the driver
+/// with the most projected points.
+pub fn score_projection(team: Team, projection: &Grid<f32>) -> TeamScore<f32> {
+    let mut score = TeamScore::new(team, |d| projection.drivers[d], |c| projection.constrs[c]);
+    score.apply_drs(Pick::Hindsight);
+    score
+}
+
+/// Points of a team as a player entered it.
+pub fn score_ext_team(team: &ExtendedTeam, week_points: &WeekPoints) -> TeamScore {
+    let rule = chip_rule(team.chip.as_ref());
+    score_team(
+        rule.as_ref(),
+        team.team,
+        Pick::Driver(team.drs_driver),
+        week_points,
+    )
+}
+
+/// Forecast of a team's points from weighted past weeks. The DRS driver is
+/// picked before the race from the predicted points, `hindsight` is what the
+/// best pick after every race would have scored.
+#[derive(Clone, Copy, Debug)]
+pub struct Evaluation {
+    pub points: f32,
+    pub hindsight: f32,
+    pub drs_driver: usize,
+}
+
+impl Evaluation {
+    /// Points the DRS pick is expected to lose against the pick in hindsight.
+    pub fn expected_loss(&self) -> f32 {
+        self.hindsight - self.points
+    }
+}
+
+/// Driver of a team with the most predicted points, the first one on ties.
+pub fn predicted_drs(team: Team, predicted: &[f32]) -> usize {
+    let mut best = team.drivers()[0];
+    for driver in team.drivers() {
+        if predicted[driver] > predicted[best] {
+            best = driver;
+        }
+    }
+    best
+}
+
+/// Evaluates a team over past weeks, each weighted by `weights[week]`.
+pub fn evaluate(
+    rule: &dyn ChipRule,
+    team: Team,
+    points: &[WeekPoints],
+    weights: &[f32],
+) -> Evaluation {
+    let size = team.drivers().into_iter().max().map_or(0, |d| d + 1);
+    let mut predicted = vec![0.0; size];
+    for (week_points, weight) in points.iter().zip(weights) {
+        for driver in team.drivers() {
+            predicted[driver] += rule.driver_points(week_points, driver) as f32 * weight;
+        }
+    }
+    let drs_driver = predicted_drs(team, &predicted);
+    let mut evaluation = Evaluation {
+        points: 0.0,
+        hindsight: 0.0,
+        drs_driver,
+    };
+    for (week_points, weight) in points.iter().zip(weights) {
+        let score = |drs| score_team(rule, team, drs, week_points).total() as f32 * weight;
+        evaluation.points += score(Pick::Driver(drs_driver));
+        evaluation.hindsight += score(Pick::Hindsight);
+    }
+    evaluation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sprint weekend of six drivers and two constructors.
+    fn week_points() -> WeekPoints {
+        let grid = |drivers: [isize; 6], constrs: [isize; 2]| Grid {
+            drivers: drivers.to_vec(),
+            constrs: constrs.to_vec(),
+        };
+        WeekPoints::new(
+            grid([20, 12, -3, 8, 5, 15], [30, -4]),
+            grid([5, 3, 0, 2, 1, 4], [0, 0]),
+            grid([0; 6], [0, 0]),
+            grid([3, 2, 0, 1, 0, 2], [0, 0]),
+            grid([0, 0, 5, 0, 2, 0], [3, 0]),
+        )
+    }
+
+    /// Drivers 0 to 4 with DRS on driver 1, and both constructors.
+    fn team(chip: Option<Chip>) -> ExtendedTeam {
+        let team = (0..5).fold(Team::new(), Team::set_driver);
+        ExtendedTeam {
+            team: team.set_constructor(0).set_constructor(1),
+            chip,
+            drs_driver: 1,
+            negative: 0,
+        }
+    }
+
+    fn total(chip: Option<Chip>) -> isize {
+        score_ext_team(&team(chip), &week_points()).total()
+    }
+
+    // The expected totals are those of the scoring before chip rules.
+    #[test]
+    fn scores_teams_without_effective_chip() {
+        assert_eq!(total(None), 80);
+        assert_eq!(total(Some(Chip::Limitless)), 80);
+        assert_eq!(total(Some(Chip::Wildcard)), 80);
+    }
+
+    #[test]
+    fn no_negative_adds_back_negative_points() {
+        assert_eq!(total(Some(Chip::NoNegative)), 90);
+    }
+
+    #[test]
+    fn auto_pilot_moves_drs_to_best_driver() {
+        let score = score_ext_team(&team(Some(Chip::AutoPilot)), &week_points());
+        assert_eq!(score.drs_driver, 0);
+        assert_eq!(score.total(), 88);
+    }
+
+    #[test]
+    fn extra_drs_triples_a_second_driver() {
+        assert_eq!(total(Some(Chip::ExtraDRS(3))), 96);
+    }
+
+    #[test]
+    fn final_fix_scores_race_of_substitute() {
+        let score = score_ext_team(&team(Some(Chip::FinalFix(2, 5))), &week_points());
+        assert_eq!(score.driver(2), Some(0));
+        assert_eq!(score.substitute, Some((5, 9)));
+        assert_eq!(score.total(), 92);
+    }
+
+    #[test]
+    fn final_fix_moves_drs_to_substitute() {
+        let score = score_ext_team(&team(Some(Chip::FinalFix(1, 5))), &week_points());
+        assert_eq!(score.driver(1), Some(10));
+        assert_eq!(score.substitute, Some((5, 18)));
+        assert_eq!(score.total(), 84);
+    }
+
+    #[test]
+    fn projection_gets_drs_on_best_driver() {
+        let projection = Grid {
+            drivers: vec![4.5, 10.25, 3.0, 1.0, 0.5],
+            constrs: vec![7.0, 2.0],
+        };
+        let team = (0..5)
+            .fold(Team::new(), Team::set_driver)
+            .set_constructor(0)
+            .set_constructor(1);
+        let score = score_projection(team, &projection);
+        assert_eq!(score.drs_driver, 1);
+        assert_eq!(score.total(), 38.5);
+    }
+}
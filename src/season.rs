@@ -0,0 +1,182 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::{
+    error::{Error, Result},
+    prices::PriceRules,
+    team::{Chip, MAX_CONSTRUCTORS, MAX_DRIVERS},
+    transfers::TransferPolicy,
+};
+
+/// Directory with the season file and all data files.
+pub const DATA_DIR: &str = "data";
+pub const SEASON_FILE: &str = "season.toml";
+
+/// Everything that changes from one season to the next: the grid, the ids
+/// used by the fantasy feeds, the race calendar and the game's transfer rules.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Season {
+    pub year: u16,
+    pub drivers: Vec<Driver>,
+    pub constructors: Vec<Constructor>,
+    pub races: Vec<Race>,
+    /// Model of the game's price changes, if the season file has one.
+    #[serde(default)]
+    pub price_changes: Option<PriceRules>,
+    #[serde(default)]
+    pub transfers: TransferPolicy,
+    /// Directory the data files of the season are in, the one of the season
+    /// file unless set otherwise.
+    #[serde(skip)]
+    pub data_dir: PathBuf,
+}
+
+/// A driver of the roster. Reserve drivers and permanent replacements are
+/// regular entries that are only available for part of the season; weeks are
+/// indices into the race calendar.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Driver {
+    pub name: String,
+    pub fantasy_id: usize,
+    #[serde(default)]
+    pub first_week: usize,
+    #[serde(default)]
+    pub last_week: Option<usize>,
+    #[serde(default)]
+    pub absent_weeks: Vec<usize>,
+    /// Name of the constructor the driver drives for, which tells drivers
+    /// with the same name apart in the player list.
+    #[serde(default)]
+    pub constructor: Option<String>,
+}
+
+impl Driver {
+    pub fn available(&self, week: usize) -> bool {
+        week >= self.first_week
+            && self.last_week.is_none_or(|last| week <= last)
+            && !self.absent_weeks.contains(&week)
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Constructor {
+    pub name: String,
+    pub short: String,
+    pub fantasy_id: usize,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Race {
+    pub name: String,
+    #[serde(default)]
+    pub sprint: bool,
+}
+
+impl Season {
+    pub fn load<P: AsRef<Path>>(file: P) -> Result<Self> {
+        let file = file.as_ref();
+        let content =
+            std::fs::read_to_string(file).map_err(|err| Error::Io(file.to_path_buf(), err))?;
+        let mut season: Season = toml::from_str(&content)
+            .map_err(|err| Error::Parse(format!("{}: {err}", file.display())))?;
+        if season.drivers.len() > MAX_DRIVERS {
+            return Err(Error::Parse(format!(
+                "{}: a season can't define more than {MAX_DRIVERS} drivers",
+                file.display()
+            )));
+        }
+        if season.constructors.len() > MAX_CONSTRUCTORS {
+            return Err(Error::Parse(format!(
+                "{}: a season can't define more than {MAX_CONSTRUCTORS} constructors",
+                file.display()
+            )));
+        }
+        if let Some((driver, constructor)) = season.drivers.iter().find_map(|d| {
+            d.constructor
+                .as_ref()
+                .filter(|c| season.constructor_index(c).is_none())
+                .map(|c| (&d.name, c))
+        }) {
+            return Err(Error::Parse(format!(
+                "{}: unknown constructor {constructor:?} of driver {driver}",
+                file.display()
+            )));
+        }
+        if let Some(band) = season
+            .price_changes
+            .iter()
+            .flat_map(|rules| &rules.bands)
+            .find(|b| b.changes.len() != b.thresholds.len() + 1)
+        {
+            return Err(Error::Parse(format!(
+                "{}: the price band from {} needs one more change than thresholds",
+                file.display(),
+                band.min_price
+            )));
+        }
+        if let Some(chip) = season
+            .transfers
+            .waived_by
+            .iter()
+            .find(|name| !Chip::NAMES.contains(&name.as_str()))
+        {
+            return Err(Error::Parse(format!(
+                "{}: unknown chip {chip:?} in transfers.waived_by",
+                file.display()
+            )));
+        }
+        season.data_dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+        Ok(season)
+    }
+
+    /// Path of a file in the season's data directory.
+    pub fn data_file(&self, name: &str) -> PathBuf {
+        self.data_dir.join(name)
+    }
+
+    pub fn driver_available(&self, driver: usize, week: usize) -> bool {
+        self.drivers[driver].available(week)
+    }
+
+    /// Drivers that can be picked in the given week.
+    pub fn available_drivers(&self, week: usize) -> Vec<usize> {
+        (0..self.drivers.len())
+            .filter(|&d| self.driver_available(d, week))
+            .collect()
+    }
+
+    pub fn driver_names(&self) -> Vec<&str> {
+        self.drivers.iter().map(|d| d.name.as_str()).collect()
+    }
+
+    pub fn constructor_names(&self) -> Vec<&str> {
+        self.constructors.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    pub fn driver_index(&self, name: &str) -> Option<usize> {
+        self.drivers.iter().position(|d| d.name == name)
+    }
+
+    pub fn constructor_index(&self, name: &str) -> Option<usize> {
+        self.constructors.iter().position(|c| c.name == name)
+    }
+
+    pub fn driver_name(&self, driver: usize) -> &str {
+        &self.drivers[driver].name
+    }
+
+    pub fn constructor_name(&self, constructor: usize) -> &str {
+        &self.constructors[constructor].name
+    }
+
+    pub fn constructor_short(&self, constructor: usize) -> &str {
+        &self.constructors[constructor].short
+    }
+}
+
+/// The season of the repository's data directory, for tests.
+#[cfg(test)]
+pub(crate) fn test_season() -> Season {
+    Season::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("data/season.toml")).unwrap()
+}
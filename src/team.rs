@@ -1,68 +1,37 @@
-use std::{collections::HashMap, fmt::Display};
-
-use crate::week::WeekPoints;
-
-pub const DRIVERS: [&str; 20] = [
-    "VER", "PER", "SAI", "LEC", "HAM", "RUS", "NOR", "PIA", "ALO", "STR", "OCO", "GAS", "RIC",
-    "TSU", "ALB", "SAR", "MAG", "HUL", "BOT", "ZHO",
-];
-pub const CONSTRUCTORS: [&str; 10] = [
-    "RedBull",
-    "Ferrari",
-    "Mercedes",
-    "McLaren",
-    "AstonMartin",
-    "Alpine",
-    "KickSauber",
-    "Haas",
-    "VCARB",
-    "Williams",
-];
-// const DRIVERS_MAP: HashMap<&str, usize> = HashMap::from_iter([("VER", 0), ("PER", 1), ("SAI", 2), ("LEC", 3), ("HAM", 4), ("RUS", 5), ("NOR", 6), ("PIA", 7), ("ALO", 8), ("STR", 9), ("OCO", 10), ("GAS", 11), ("RIC", 12)]);
-pub const CONSTRUCTORS_SHORT: [&str; 10] = [
-    "RB", "FER", "MER", "MCL", "AST", "ALP", "KIK", "HAS", "VCA", "WIL",
-];
-
-pub fn driver_from_name(driver: &str) -> usize {
-    DRIVERS
-        .iter()
-        .enumerate()
-        .find_map(|(index, &name)| if name == driver { Some(index) } else { None })
-        .unwrap_or_else(|| panic!("Invalid driver name: {driver}"))
+use crate::{
+    error::{Error, Result},
+    season::Season,
+};
+
+/// Drivers occupy the lower half of the team bitmap, constructors the upper.
+pub const MAX_DRIVERS: usize = 32;
+pub const MAX_CONSTRUCTORS: usize = 32;
+
+pub fn driver_from_name(season: &Season, driver: &str) -> Result<usize> {
+    season
+        .driver_index(driver)
+        .ok_or_else(|| Error::UnknownDriver(driver.to_string()))
 }
 
-pub fn constructor_from_name(constructor: &str) -> usize {
-    CONSTRUCTORS
-        .iter()
-        .enumerate()
-        .find_map(|(index, &name)| {
-            if name == constructor {
-                Some(index)
-            } else {
-                None
-            }
-        })
-        .expect("Invalid constructor name")
+pub fn constructor_from_name(season: &Season, constructor: &str) -> Result<usize> {
+    season
+        .constructor_index(constructor)
+        .ok_or_else(|| Error::UnknownConstructor(constructor.to_string()))
 }
 
 #[repr(transparent)]
 #[derive(Copy, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
-pub struct Team(u32);
+pub struct Team(u64);
 
 impl std::fmt::Debug for Team {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{:030b}", self.0))
+        f.write_fmt(format_args!("{:064b}", self.0))
     }
 }
 
-impl Display for Team {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{:?},{:?}",
-            self.drivers().map(|d| DRIVERS[d]),
-            self.constructors().map(|c| CONSTRUCTORS[c])
-        )
+impl Default for Team {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -75,18 +44,18 @@ impl Team {
         Self(self.0 | (1 << driver))
     }
 
-    pub fn set_driver_name(self, driver: &str) -> Self {
-        let index = driver_from_name(driver);
-        self.set_driver(index)
+    pub fn set_driver_name(self, season: &Season, driver: &str) -> Result<Self> {
+        let index = driver_from_name(season, driver)?;
+        Ok(self.set_driver(index))
     }
 
     pub fn set_constructor(self, constructor: usize) -> Self {
-        Self(self.0 | (1 << (constructor + 20)))
+        Self(self.0 | (1 << (constructor + MAX_DRIVERS)))
     }
 
-    pub fn set_constructor_name(self, constructor: &str) -> Self {
-        let index = constructor_from_name(constructor);
-        self.set_constructor(index)
+    pub fn set_constructor_name(self, season: &Season, constructor: &str) -> Result<Self> {
+        let index = constructor_from_name(season, constructor)?;
+        Ok(self.set_constructor(index))
     }
 
     pub fn toggle_driver(self, driver: usize) -> Self {
@@ -94,13 +63,13 @@ impl Team {
     }
 
     pub fn toggle_constructor(self, constructor: usize) -> Self {
-        Self(self.0 ^ (1 << (constructor + 20)))
+        Self(self.0 ^ (1 << (constructor + MAX_DRIVERS)))
     }
 
     pub fn drivers(self) -> [usize; 5] {
         let mut index = 0;
         let mut arr = [0; 5];
-        for shift in 0..20 {
+        for shift in 0..MAX_DRIVERS {
             if (self.0 >> shift) & 1 == 1 {
                 arr[index] = shift;
                 index += 1;
@@ -112,21 +81,25 @@ impl Team {
     pub fn constructors(self) -> [usize; 2] {
         let mut index = 0;
         let mut arr = [0; 2];
-        for shift in 20..30 {
+        for shift in MAX_DRIVERS..MAX_DRIVERS + MAX_CONSTRUCTORS {
             if (self.0 >> shift) & 1 == 1 {
-                arr[index] = shift - 20;
+                arr[index] = shift - MAX_DRIVERS;
                 index += 1;
             }
         }
         arr
     }
 
-    pub fn bitmap(self) -> u32 {
+    pub fn bitmap(self) -> u64 {
         self.0
     }
 }
 
+/// Enumerates every team that can be picked in a week, i.e. all combinations
+/// of five available drivers and two constructors.
 pub struct TeamEnumeration {
+    drivers: Vec<usize>,
+    constructors: Vec<usize>,
     ids: [usize; 7],
     first: bool,
 }
@@ -134,55 +107,61 @@ pub struct TeamEnumeration {
 const DEFAULT_IDS: [usize; 7] = [0, 1, 2, 3, 4, 0, 1];
 
 impl TeamEnumeration {
-    pub fn new() -> Self {
+    pub fn new(season: &Season, week: usize) -> Self {
         Self {
+            drivers: season.available_drivers(week),
+            constructors: (0..season.constructors.len()).collect(),
             ids: DEFAULT_IDS,
             first: true,
         }
     }
+
+    /// Like `new`, with the drivers of `team` as well even when they miss the
+    /// week, since a player may keep a driver through an absence.
+    pub fn keeping(season: &Season, week: usize, team: Team) -> Self {
+        let mut enumeration = Self::new(season, week);
+        enumeration.drivers.extend(team.drivers());
+        enumeration.drivers.sort_unstable();
+        enumeration.drivers.dedup();
+        enumeration
+    }
 }
 
 impl Iterator for TeamEnumeration {
     type Item = Team;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.drivers.len() < 5 || self.constructors.len() < 2 {
+            return None;
+        }
         if self.first {
             self.first = false;
         } else {
             let mut last_index = 0;
             for i in 0..7 {
                 last_index = i;
-                if i == 6 {
-                    if self.ids[i] == 9 {
-                        return None;
-                    }
+                let limit = match i {
+                    4 => self.drivers.len(),
+                    6 => self.constructors.len(),
+                    _ => self.ids[i + 1],
+                };
+                if self.ids[i] + 1 < limit {
                     self.ids[i] += 1;
                     break;
                 }
-                if i == 4 {
-                    if self.ids[i] < 19 {
-                        self.ids[i] += 1;
-                        break;
-                    }
-                } else if self.ids[i] + 1 < self.ids[i + 1]
-                    && ((i < 5 && self.ids[i] < 19) || self.ids[i] < 9)
-                {
-                    self.ids[i] += 1;
-                    break;
+                if i == 6 {
+                    return None;
                 }
             }
-            for j in 0..last_index {
-                self.ids[j] = DEFAULT_IDS[j];
-            }
+            self.ids[..last_index].copy_from_slice(&DEFAULT_IDS[..last_index]);
         }
         let mut team = Team::new();
-        team = team.set_driver(self.ids[0]);
-        team = team.set_driver(self.ids[1]);
-        team = team.set_driver(self.ids[2]);
-        team = team.set_driver(self.ids[3]);
-        team = team.set_driver(self.ids[4]);
-        team = team.set_constructor(self.ids[5]);
-        team = team.set_constructor(self.ids[6]);
+        for &id in &self.ids[..5] {
+            team = team.set_driver(self.drivers[id]);
+        }
+        for &id in &self.ids[5..] {
+            team = team.set_constructor(self.constructors[id]);
+        }
         Some(team)
     }
 }
@@ -206,22 +185,58 @@ pub enum Chip {
 }
 
 impl Chip {
-    pub fn from_input(value: &str) -> Option<Self> {
+    pub fn from_input(season: &Season, value: &str) -> Result<Option<Self>> {
         let vals = value.split_ascii_whitespace().collect::<Vec<_>>();
-        match vals[0] {
+        let driver = |i: usize| {
+            vals.get(i)
+                .ok_or_else(|| Error::Parse(format!("chip {value:?} is missing a driver")))
+                .and_then(|name| driver_from_name(season, name))
+        };
+        let chip = match vals.first().copied().unwrap_or_default() {
             "Limitless" => Some(Self::Limitless),
             "Wildcard" => Some(Self::Wildcard),
             "AutoPilot" => Some(Self::AutoPilot),
             "NoNegative" => Some(Self::NoNegative),
-            "ExtraDRS" => Some(Self::ExtraDRS(
-                driver_from_name(vals[1]),
-            )),
-            "FinalFix" => Some(Self::FinalFix(
-                driver_from_name(vals[1]),
-                driver_from_name(vals[2]),
-            )),
+            "ExtraDRS" => Some(Self::ExtraDRS(driver(1)?)),
+            "FinalFix" => Some(Self::FinalFix(driver(1)?, driver(2)?)),
             "None" => None,
-            _ => panic!("Invalid Chip: {value}"),
+            _ => return Err(Error::UnknownChip(value.to_string())),
+        };
+        Ok(chip)
+    }
+
+    /// Names of the chips as written in the league files.
+    pub const NAMES: [&'static str; 6] = [
+        "Limitless",
+        "Wildcard",
+        "FinalFix",
+        "AutoPilot",
+        "NoNegative",
+        "ExtraDRS",
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Chip::Limitless => "Limitless",
+            Chip::Wildcard => "Wildcard",
+            Chip::FinalFix(_, _) => "FinalFix",
+            Chip::AutoPilot => "AutoPilot",
+            Chip::NoNegative => "NoNegative",
+            Chip::ExtraDRS(_) => "ExtraDRS",
+        }
+    }
+
+    /// Formats the chip the way [`Chip::from_input`] reads it.
+    pub fn to_input(&self, season: &Season) -> String {
+        match self {
+            Chip::ExtraDRS(driver) => format!("{} {}", self.name(), season.driver_name(*driver)),
+            Chip::FinalFix(out, sub) => format!(
+                "{} {} {}",
+                self.name(),
+                season.driver_name(*out),
+                season.driver_name(*sub)
+            ),
+            _ => self.name().to_string(),
         }
     }
 
@@ -0,0 +1,179 @@
+use serde::Deserialize;
+
+use crate::team::{Chip, ExtendedTeam, Team};
+
+/// Transfer rules of the game. Part of the season file, as they change from
+/// one season to the next.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TransferPolicy {
+    /// Free transfers every week.
+    pub free_per_week: usize,
+    /// Unused free transfers that carry over to the next week.
+    pub max_banked: usize,
+    /// Points lost for every transfer beyond the free ones.
+    pub penalty: isize,
+    /// Chips that make all transfers of their week free, by the name used in
+    /// the league files.
+    pub waived_by: Vec<String>,
+}
+
+impl Default for TransferPolicy {
+    fn default() -> Self {
+        Self {
+            free_per_week: 2,
+            max_banked: 1,
+            penalty: 10,
+            waived_by: vec!["Wildcard".to_string(), "Limitless".to_string()],
+        }
+    }
+}
+
+impl TransferPolicy {
+    pub fn waives(&self, chip: Option<&Chip>) -> bool {
+        chip.is_some_and(|chip| self.waived_by.iter().any(|name| name == chip.name()))
+    }
+
+    /// Penalty for making `transfers` transfers with `free` free ones.
+    pub fn penalty(&self, transfers: usize, free: usize) -> isize {
+        transfers.saturating_sub(free) as isize * self.penalty
+    }
+
+    /// Free transfers of the next week after making `transfers` with `free`.
+    pub fn next_free(&self, free: usize, transfers: usize) -> usize {
+        let banked = free.saturating_sub(transfers).min(self.max_banked);
+        self.free_per_week + banked
+    }
+}
+
+/// Transfers a team made before a week's race.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WeekTransfers {
+    pub transfers: usize,
+    pub free: usize,
+    pub penalty: isize,
+}
+
+/// Number of drivers and constructors of `to` that aren't part of `from`.
+pub fn transfers_between(from: Team, to: Team) -> usize {
+    (to.bitmap() & !from.bitmap()).count_ones() as usize
+}
+
+/// Team a week's transfers are counted from: the team of the week before,
+/// with a Final Fix replacement staying in the team. After Limitless the team
+/// reverts to the one before it.
+pub fn previous_team(teams: &[ExtendedTeam], week: usize) -> Option<Team> {
+    let t = teams[..week]
+        .iter()
+        .rev()
+        .find(|t| !matches!(t.chip, Some(Chip::Limitless)))?;
+    Some(match t.chip {
+        Some(Chip::FinalFix(out, sub)) => t.team.toggle_driver(out).toggle_driver(sub),
+        _ => t.team,
+    })
+}
+
+/// Derives the transfers and penalties of every week from consecutive teams.
+/// The first team is picked for free and weeks with a chip that waives the
+/// penalty don't bank unused transfers.
+pub fn transfer_history(policy: &TransferPolicy, teams: &[ExtendedTeam]) -> Vec<WeekTransfers> {
+    replay(policy, teams).0
+}
+
+/// Free transfers for the week after the last of `teams`.
+pub fn free_transfers_after(policy: &TransferPolicy, teams: &[ExtendedTeam]) -> usize {
+    replay(policy, teams).1
+}
+
+fn replay(policy: &TransferPolicy, teams: &[ExtendedTeam]) -> (Vec<WeekTransfers>, usize) {
+    let mut history = Vec::with_capacity(teams.len());
+    let mut free = policy.free_per_week;
+    for (week, t) in teams.iter().enumerate() {
+        let previous = previous_team(teams, week);
+        let transfers = previous.map_or(0, |p| transfers_between(p, t.team));
+        let waived = previous.is_none() || policy.waives(t.chip.as_ref());
+        history.push(WeekTransfers {
+            transfers,
+            free,
+            penalty: if waived {
+                0
+            } else {
+                policy.penalty(transfers, free)
+            },
+        });
+        free = if waived {
+            policy.free_per_week
+        } else {
+            policy.next_free(free, transfers)
+        };
+    }
+    (history, free)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn team(drivers: [usize; 5], chip: Option<Chip>) -> ExtendedTeam {
+        let team = drivers.into_iter().fold(Team::new(), Team::set_driver);
+        ExtendedTeam {
+            team: team.set_constructor(0).set_constructor(1),
+            chip,
+            drs_driver: drivers[0],
+            negative: 0,
+        }
+    }
+
+    fn week(transfers: usize, free: usize, penalty: isize) -> WeekTransfers {
+        WeekTransfers {
+            transfers,
+            free,
+            penalty,
+        }
+    }
+
+    #[test]
+    fn banks_unused_transfers_and_charges_extra_ones() {
+        let teams = [
+            team([0, 1, 2, 3, 4], None),
+            team([0, 1, 2, 3, 4], None),
+            team([0, 5, 6, 7, 8], None),
+            team([0, 5, 6, 7, 9], None),
+        ];
+        let policy = TransferPolicy::default();
+
+        assert_eq!(
+            transfer_history(&policy, &teams),
+            [week(0, 2, 0), week(0, 2, 0), week(4, 3, 10), week(1, 2, 0)]
+        );
+        assert_eq!(free_transfers_after(&policy, &teams), 3);
+    }
+
+    #[test]
+    fn limitless_team_reverts() {
+        let teams = [
+            team([0, 1, 2, 3, 4], None),
+            team([5, 6, 7, 8, 9], Some(Chip::Limitless)),
+            team([0, 1, 2, 3, 5], None),
+        ];
+
+        assert_eq!(
+            transfer_history(&TransferPolicy::default(), &teams),
+            [week(0, 2, 0), week(5, 2, 0), week(1, 2, 0)]
+        );
+    }
+
+    #[test]
+    fn final_fix_substitute_stays_in_team() {
+        let teams = [
+            team([0, 1, 2, 3, 4], Some(Chip::FinalFix(4, 5))),
+            team([0, 1, 2, 3, 5], None),
+        ];
+
+        assert_eq!(previous_team(&teams, 1), Some(teams[1].team),);
+        assert_eq!(
+            transfer_history(&TransferPolicy::default(), &teams)[1],
+            week(0, 2, 0)
+        );
+    }
+}
@@ -0,0 +1,380 @@
+use std::{mem::discriminant, path::Path};
+
+use crate::{
+    data::{self, DataError, DataErrorKind},
+    error::{Error, Result},
+    events,
+    season::Season,
+    team::Chip,
+    transfers,
+};
+
+/// Findings of [`validate`]. Problems make the data unusable, warnings point
+/// at data that is probably wrong, like a transfer penalty that doesn't
+/// match the teams.
+pub struct Report {
+    pub problems: Vec<DataError>,
+    pub warnings: Vec<DataError>,
+}
+
+/// Checks the season's data files and all league files for consistency and
+/// returns everything found.
+pub fn validate(season: &Season) -> Result<Report> {
+    let mut violations = Vec::new();
+    let mut warnings = Vec::new();
+    let weeks = validate_week_counts(season, &mut violations);
+    validate_sprint_weeks(season, &mut violations, &mut warnings);
+    validate_events(season, &mut violations);
+    for file in data::league_files(season)? {
+        validate_player_file(season, &file, weeks, &mut violations);
+        validate_transfers(season, &file, &mut warnings);
+    }
+    Ok(Report {
+        problems: violations,
+        warnings,
+    })
+}
+
+fn inconsistent(file: &Path, line: Option<u64>, message: String) -> DataError {
+    DataError::new(file, DataErrorKind::Inconsistent(message)).at_line(line)
+}
+
+fn into_data_error(file: &Path, err: Error) -> DataError {
+    match err {
+        Error::Data(err) => err,
+        err => DataError::new(file, DataErrorKind::Invalid(Box::new(err))),
+    }
+}
+
+/// Checks that all points files cover the same weeks and that the costs files
+/// have one more row, the prices for the upcoming week. Returns the number of
+/// weeks with points.
+fn validate_week_counts(season: &Season, violations: &mut Vec<DataError>) -> Option<usize> {
+    let points_files = [
+        (
+            season.data_file(data::DRIVER_POINTS_FILE),
+            data::driver_points(season).map(|r| r.len()),
+        ),
+        (
+            season.data_file(data::CONSTRUCTOR_POINTS_FILE),
+            data::constructor_points(season).map(|r| r.len()),
+        ),
+        (
+            season.data_file(data::DRIVER_QUALI_FILE),
+            data::driver_qualifying_points(season).map(|r| r.len()),
+        ),
+        (
+            season.data_file(data::DRIVER_SPRINT_QUALI_FILE),
+            data::driver_sprint_qualifying_points(season).map(|r| r.len()),
+        ),
+        (
+            season.data_file(data::DRIVER_SPRINT_FILE),
+            data::driver_sprint_points(season).map(|r| r.len()),
+        ),
+        (
+            season.data_file(data::DRIVER_NEGATIVE_FILE),
+            data::driver_negative_points(season).map(|r| r.len()),
+        ),
+        (
+            season.data_file(data::CONSTRUCTOR_NEGATIVE_FILE),
+            data::constructor_negative_points(season).map(|r| r.len()),
+        ),
+    ];
+    let cost_files = [
+        (
+            season.data_file(data::DRIVER_COST_FILE),
+            data::driver_costs(season).map(|r| r.len()),
+        ),
+        (
+            season.data_file(data::CONSTRUCTOR_COST_FILE),
+            data::constructor_costs(season).map(|r| r.len()),
+        ),
+    ];
+
+    let mut weeks = None;
+    for (file, rows) in points_files {
+        match (rows, &weeks) {
+            (Err(err), _) => violations.push(into_data_error(&file, err)),
+            (Ok(rows), None) => weeks = Some((rows, file)),
+            (Ok(rows), Some((expected, reference))) if rows != *expected => {
+                violations.push(inconsistent(
+                    &file,
+                    None,
+                    format!(
+                        "has {rows} weeks, but {} has {expected}",
+                        reference.display()
+                    ),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    for (file, rows) in cost_files {
+        match (rows, &weeks) {
+            (Err(err), _) => violations.push(into_data_error(&file, err)),
+            (Ok(rows), Some((expected, reference))) if rows != expected + 1 => {
+                violations.push(inconsistent(
+                    &file,
+                    None,
+                    format!(
+                        "has {rows} rows, but needs {} for the {expected} weeks of {}",
+                        expected + 1,
+                        reference.display()
+                    ),
+                ));
+            }
+            _ => {}
+        }
+    }
+    weeks.map(|(weeks, _)| weeks)
+}
+
+/// Checks that sprint points only appear on weekends marked as sprint
+/// weekends in the calendar, and warns about sprint weekends with blank
+/// cells, which are read as 0 and leave the sprint points in the race points.
+fn validate_sprint_weeks(
+    season: &Season,
+    violations: &mut Vec<DataError>,
+    warnings: &mut Vec<DataError>,
+) {
+    let sprint_files = [
+        (
+            season.data_file(data::DRIVER_SPRINT_QUALI_FILE),
+            data::driver_sprint_qualifying_points(season),
+        ),
+        (
+            season.data_file(data::DRIVER_SPRINT_FILE),
+            data::driver_sprint_points(season),
+        ),
+    ];
+    for (file, rows) in sprint_files {
+        let Ok(rows) = rows else {
+            continue;
+        };
+        let blank = blank_rows(&file);
+        for (week, row) in rows.iter().enumerate() {
+            let sprint = season.races.get(week).is_some_and(|race| race.sprint);
+            if !sprint && row.iter().any(|&points| points != 0) {
+                violations.push(inconsistent(
+                    &file,
+                    Some(week as u64 + 2),
+                    format!("sprint points in week {week}, which isn't a sprint weekend"),
+                ));
+            }
+            if sprint && blank.get(week).copied().unwrap_or_default() {
+                warnings.push(inconsistent(
+                    &file,
+                    Some(week as u64 + 2),
+                    format!("blank sprint points in week {week}, which is a sprint weekend"),
+                ));
+            }
+        }
+    }
+}
+
+/// Whether each data row of a file has a blank cell. Files that can't be
+/// read have no rows, reading them is reported elsewhere.
+fn blank_rows(file: &Path) -> Vec<bool> {
+    let Ok(mut reader) = csv::Reader::from_path(file) else {
+        return Vec::new();
+    };
+    reader
+        .records()
+        .map_while(|record| record.ok())
+        .map(|r| r.iter().any(str::is_empty))
+        .collect()
+}
+
+/// Checks that the scoring events add up to the points files for every week
+/// that has events.
+fn validate_events(season: &Season, violations: &mut Vec<DataError>) {
+    let file = season.data_file(events::EVENTS_FILE);
+    let file = file.as_path();
+    if !file.exists() {
+        return;
+    }
+    let events = match events::read_events(season) {
+        Ok(events) => events,
+        Err(err) => {
+            violations.push(into_data_error(file, err));
+            return;
+        }
+    };
+    let Ok(points) = data::points(season) else {
+        return;
+    };
+    let rebuilt = events::points_from_events(season, &events);
+    for (week, week_points) in rebuilt.iter().enumerate() {
+        if !events.iter().any(|e| e.week == week) {
+            continue;
+        }
+        let Some(expected) = points.get(week) else {
+            violations.push(inconsistent(
+                file,
+                None,
+                format!("has events for week {week}, which has no points"),
+            ));
+            continue;
+        };
+        let grids = [
+            ("total", &week_points.total, &expected.total),
+            ("qualifying", &week_points.qualifying, &expected.qualifying),
+            (
+                "sprint qualifying",
+                &week_points.sprint_qualifying,
+                &expected.sprint_qualifying,
+            ),
+            ("sprint", &week_points.sprint, &expected.sprint),
+            ("negative", &week_points.negative, &expected.negative),
+        ];
+        for (name, rebuilt, expected) in grids {
+            if rebuilt != expected {
+                violations.push(inconsistent(
+                    file,
+                    None,
+                    format!("{name} points of week {week} don't match the points files"),
+                ));
+            }
+        }
+    }
+}
+
+fn validate_player_file(
+    season: &Season,
+    file: &Path,
+    weeks: Option<usize>,
+    violations: &mut Vec<DataError>,
+) {
+    let mut reader = match csv::ReaderBuilder::new().has_headers(true).from_path(file) {
+        Ok(reader) => reader,
+        Err(err) => {
+            violations.push(DataError::new(file, DataErrorKind::Csv(err)));
+            return;
+        }
+    };
+
+    let mut used_chips: Vec<(Chip, Option<u64>)> = Vec::new();
+    let mut rows = 0;
+    for (week, record) in reader.records().enumerate() {
+        let r = match record {
+            Ok(r) => r,
+            Err(err) => {
+                violations.push(DataError::new(file, DataErrorKind::Csv(err)));
+                continue;
+            }
+        };
+        rows += 1;
+        let line = r.position().map(|p| p.line());
+        let mut report = |message: String| violations.push(inconsistent(file, line, message));
+        if r.len() < 10 {
+            report(format!("expected 10 columns, found {}", r.len()));
+            continue;
+        }
+
+        let mut drivers = Vec::new();
+        for name in r.iter().take(5) {
+            match season.driver_index(name) {
+                Some(driver) if drivers.contains(&driver) => {
+                    report(format!("driver {name} is picked twice"))
+                }
+                Some(driver) => drivers.push(driver),
+                None => report(format!("unknown driver {name:?}")),
+            }
+        }
+
+        let mut constructors = Vec::new();
+        for name in r.iter().skip(5).take(2) {
+            match season.constructor_index(name) {
+                Some(constructor) if constructors.contains(&constructor) => {
+                    report(format!("constructor {name} is picked twice"))
+                }
+                Some(constructor) => constructors.push(constructor),
+                None => report(format!("unknown constructor {name:?}")),
+            }
+        }
+
+        match season.driver_index(&r[7]) {
+            Some(drs) if !drivers.contains(&drs) => {
+                report(format!("DRS driver {} isn't part of the team", &r[7]))
+            }
+            Some(_) => {}
+            None => report(format!("unknown DRS driver {:?}", &r[7])),
+        }
+
+        match Chip::from_input(season, &r[8]) {
+            Ok(Some(chip)) => {
+                if let Chip::FinalFix(out, sub) = chip {
+                    if !drivers.contains(&out) {
+                        report(format!(
+                            "Final Fix replaces {}, who isn't part of the team",
+                            season.driver_name(out)
+                        ));
+                    }
+                    if drivers.contains(&sub) {
+                        report(format!(
+                            "Final Fix brings in {}, who is already part of the team",
+                            season.driver_name(sub)
+                        ));
+                    }
+                    if !season.driver_available(sub, week) {
+                        report(format!(
+                            "Final Fix driver {} isn't available in week {week}",
+                            season.driver_name(sub)
+                        ));
+                    }
+                }
+                if let Some((_, first)) = used_chips
+                    .iter()
+                    .find(|(used, _)| discriminant(used) == discriminant(&chip))
+                {
+                    report(format!(
+                        "chip {} was already used on line {}",
+                        chip.short_name(),
+                        first.unwrap_or_default()
+                    ));
+                } else {
+                    used_chips.push((chip, line));
+                }
+            }
+            Ok(None) => {}
+            Err(err) => report(err.to_string()),
+        }
+
+        if r[9].parse::<isize>().is_err() {
+            report(format!("invalid transfer penalty {:?}", &r[9]));
+        }
+    }
+
+    if let Some(weeks) = weeks.filter(|&weeks| rows > weeks) {
+        violations.push(inconsistent(
+            file,
+            None,
+            format!("has {rows} weeks, but there are only points for {weeks}"),
+        ));
+    }
+}
+
+/// Checks the transfer penalties entered in a league file against the ones
+/// derived from the weekly teams.
+fn validate_transfers(season: &Season, file: &Path, violations: &mut Vec<DataError>) {
+    let Some(name) = file.file_stem().and_then(|s| s.to_str()) else {
+        return;
+    };
+    let Ok(teams) = data::player_data(season, name) else {
+        return;
+    };
+    let history = transfers::transfer_history(&season.transfers, &teams);
+    for (week, (team, derived)) in teams.iter().zip(history).enumerate() {
+        if team.negative != derived.penalty {
+            violations.push(inconsistent(
+                file,
+                Some(week as u64 + 2),
+                format!(
+                    "transfer penalty is {}, but {} transfer(s) with {} free cost {}",
+                    team.negative, derived.transfers, derived.free, derived.penalty
+                ),
+            ));
+        }
+    }
+}